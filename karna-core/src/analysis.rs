@@ -0,0 +1,368 @@
+//! Static analysis of SQL text.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    CreateTable, Expr, ObjectName, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
+    Visit, Visitor,
+};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+
+use crate::error::Result;
+
+/// `WITH` clause of one query being visited.
+struct CteFrame {
+    names: Vec<String>,
+    bodies: Vec<*const Query>,
+    /// How many of `names` are in scope: a CTE body sees only the CTEs
+    /// before it (and itself when recursive), the main query sees them all.
+    visible: usize,
+    recursive: bool,
+}
+
+/// Tracks which `WITH` names are in scope while a visitor walks a statement,
+/// so a CTE named like a table shadows it only where SQL says it does.
+///
+/// Call [`CteScopes::enter`] and [`CteScopes::exit`] from the visitor's
+/// `pre_visit_query` and `post_visit_query`.
+#[derive(Default)]
+pub(crate) struct CteScopes {
+    frames: Vec<CteFrame>,
+}
+
+impl CteScopes {
+    pub(crate) fn enter(&mut self, query: &Query) {
+        let query_ptr: *const Query = query;
+        if let Some(parent) = self.frames.last_mut()
+            && let Some(i) = parent.bodies.iter().position(|&b| b == query_ptr)
+        {
+            parent.visible = i + usize::from(parent.recursive);
+        }
+        let ctes = query.with.as_ref().map_or(&[][..], |w| &w.cte_tables);
+        self.frames.push(CteFrame {
+            names: ctes
+                .iter()
+                .map(|cte| cte.alias.name.value.to_lowercase())
+                .collect(),
+            bodies: ctes.iter().map(|cte| &*cte.query as *const Query).collect(),
+            visible: 0,
+            recursive: query.with.as_ref().is_some_and(|w| w.recursive),
+        });
+    }
+
+    pub(crate) fn exit(&mut self, query: &Query) {
+        self.frames.pop();
+        let query_ptr: *const Query = query;
+        if let Some(parent) = self.frames.last_mut()
+            && let Some(i) = parent.bodies.iter().position(|&b| b == query_ptr)
+        {
+            parent.visible = i + 1;
+        }
+    }
+
+    /// Whether `name` refers to a CTE in scope rather than a table.
+    pub(crate) fn resolves(&self, name: &ObjectName) -> bool {
+        let [ident] = name.0.as_slice() else {
+            return false;
+        };
+        let name = ident.value.to_lowercase();
+        self.frames
+            .iter()
+            .any(|frame| frame.names[..frame.visible].contains(&name))
+    }
+}
+
+#[derive(Default)]
+struct TableCollector {
+    tables: BTreeSet<String>,
+    ctes: CteScopes,
+}
+
+impl Visitor for TableCollector {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        self.ctes.enter(query);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        self.ctes.exit(query);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<Self::Break> {
+        // Table functions such as `read_csv(...)` share the variant but carry args.
+        if let TableFactor::Table {
+            name, args: None, ..
+        } = factor
+            && !self.ctes.resolves(name)
+            && let Some(table) = name.0.last()
+        {
+            self.tables.insert(table.value.to_lowercase());
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Returns the lowercased, unqualified names of the tables a query reads from.
+///
+/// References to `WITH` names in scope are excluded; a CTE's own body and
+/// earlier CTEs still see the tables it shadows.
+pub fn referenced_tables(sql: &str) -> Result<BTreeSet<String>> {
+    let statements = Parser::parse_sql(&DuckDbDialect {}, sql)?;
+    let mut collector = TableCollector::default();
+    let _ = statements.visit(&mut collector);
+    Ok(collector.tables)
+}
+
+/// A column of a source table read by a query.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourceColumn {
+    /// `None` when the column can't be attributed to one table, e.g. an
+    /// unqualified name in a join whose table schemas weren't supplied.
+    pub table: Option<String>,
+    pub column: String,
+}
+
+/// The source columns feeding one output column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnLineage {
+    pub column: String,
+    pub sources: BTreeSet<SourceColumn>,
+}
+
+#[derive(Default)]
+struct ColumnRefs {
+    refs: Vec<Vec<String>>,
+}
+
+impl Visitor for ColumnRefs {
+    type Break = ();
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Identifier(ident) => self.refs.push(vec![ident.value.to_lowercase()]),
+            Expr::CompoundIdentifier(idents) => self
+                .refs
+                .push(idents.iter().map(|i| i.value.to_lowercase()).collect()),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Tables in a `SELECT`'s `FROM` clause, as `(name or alias, table)`.
+fn from_tables(select: &Select) -> Vec<(String, String)> {
+    select
+        .from
+        .iter()
+        .flat_map(|t| std::iter::once(&t.relation).chain(t.joins.iter().map(|j| &j.relation)))
+        .filter_map(|factor| match factor {
+            TableFactor::Table { name, alias, .. } => {
+                let table = name.0.last()?.value.to_lowercase();
+                let binding = alias
+                    .as_ref()
+                    .map_or_else(|| table.clone(), |a| a.name.value.to_lowercase());
+                Some((binding, table))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Derives which source columns feed each output column of a `SELECT` or
+/// `CREATE TABLE ... AS SELECT`.
+///
+/// `schemas` maps lowercased table names to their columns; it is used to
+/// expand `*` and to attribute unqualified names in joins. Only the outermost
+/// `SELECT` is resolved: columns read through subqueries in `FROM` get no
+/// table, and CTE names are reported as if they were tables.
+pub fn column_lineage(
+    sql: &str,
+    schemas: &HashMap<String, Vec<String>>,
+) -> Result<Vec<ColumnLineage>> {
+    let statements = Parser::parse_sql(&DuckDbDialect {}, sql)?;
+    let query = match statements.last() {
+        Some(Statement::Query(query)) => query,
+        Some(Statement::CreateTable(CreateTable {
+            query: Some(query), ..
+        })) => query,
+        _ => return Ok(Vec::new()),
+    };
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return Ok(Vec::new());
+    };
+    let tables = from_tables(select);
+    let columns_of = |table: &str| schemas.get(table).map(Vec::as_slice).unwrap_or_default();
+    let resolve = |parts: &[String]| -> SourceColumn {
+        let column = parts.last().cloned().unwrap_or_default();
+        let table = match parts {
+            [.., qualifier, _] => tables
+                .iter()
+                .find(|(binding, _)| binding == qualifier)
+                .map(|(_, table)| table.clone()),
+            _ if tables.len() == 1 => Some(tables[0].1.clone()),
+            _ => {
+                let mut owners = tables.iter().filter(|(_, t)| {
+                    columns_of(t)
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(&column))
+                });
+                match (owners.next(), owners.next()) {
+                    (Some((_, table)), None) => Some(table.clone()),
+                    _ => None,
+                }
+            }
+        };
+        SourceColumn { table, column }
+    };
+    let direct = |table: &str, column: &str| ColumnLineage {
+        column: column.to_string(),
+        sources: BTreeSet::from([SourceColumn {
+            table: Some(table.to_string()),
+            column: column.to_lowercase(),
+        }]),
+    };
+
+    let mut lineage = Vec::new();
+    for item in &select.projection {
+        let (expr, name) = match item {
+            SelectItem::UnnamedExpr(expr) => {
+                let name = match expr {
+                    Expr::Identifier(ident) => ident.value.clone(),
+                    Expr::CompoundIdentifier(idents) => {
+                        idents.last().map(|i| i.value.clone()).unwrap_or_default()
+                    }
+                    other => other.to_string(),
+                };
+                (expr, name)
+            }
+            SelectItem::ExprWithAlias { expr, alias } => (expr, alias.value.clone()),
+            SelectItem::Wildcard(_) => {
+                for (_, table) in &tables {
+                    lineage.extend(columns_of(table).iter().map(|c| direct(table, c)));
+                }
+                continue;
+            }
+            SelectItem::QualifiedWildcard(name, _) => {
+                let qualifier = name.0.last().map(|i| i.value.to_lowercase());
+                if let Some((_, table)) = tables.iter().find(|(b, _)| Some(b) == qualifier.as_ref())
+                {
+                    lineage.extend(columns_of(table).iter().map(|c| direct(table, c)));
+                }
+                continue;
+            }
+        };
+        let mut refs = ColumnRefs::default();
+        let _ = expr.visit(&mut refs);
+        lineage.push(ColumnLineage {
+            column: name,
+            sources: refs.refs.iter().map(|parts| resolve(parts)).collect(),
+        });
+    }
+    Ok(lineage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_tables_across_joins_and_subqueries() {
+        let tables = referenced_tables(
+            "SELECT * FROM main.Sales s JOIN stores ON s.id = stores.id \
+             WHERE s.id IN (SELECT id FROM returns)",
+        )
+        .unwrap();
+        assert_eq!(
+            tables.into_iter().collect::<Vec<_>>(),
+            ["returns", "sales", "stores"]
+        );
+    }
+
+    #[test]
+    fn skips_ctes_and_table_functions() {
+        let tables = referenced_tables(
+            "WITH recent AS (SELECT * FROM orders) \
+             SELECT * FROM recent, read_csv('extra.csv')",
+        )
+        .unwrap();
+        assert_eq!(tables.into_iter().collect::<Vec<_>>(), ["orders"]);
+    }
+
+    #[test]
+    fn resolves_ctes_per_scope() {
+        for sql in [
+            "WITH sales AS (SELECT * FROM sales WHERE amount > 0) SELECT * FROM sales",
+            "WITH a AS (SELECT * FROM b), b AS (SELECT 1 AS x) SELECT * FROM a",
+            "SELECT * FROM (WITH b AS (SELECT 1) SELECT * FROM b) t JOIN b USING (x)",
+        ] {
+            let tables = referenced_tables(sql).unwrap();
+            assert_eq!(tables.len(), 1, "{sql}");
+        }
+        let tables = referenced_tables(
+            "WITH RECURSIVE t AS (SELECT 1 AS n UNION ALL SELECT n + 1 FROM t) SELECT * FROM t",
+        )
+        .unwrap();
+        assert!(tables.is_empty());
+    }
+
+    fn sources(lineage: &ColumnLineage) -> Vec<(Option<&str>, &str)> {
+        lineage
+            .sources
+            .iter()
+            .map(|s| (s.table.as_deref(), s.column.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn traces_output_columns_to_source_columns() {
+        let schemas = HashMap::from([
+            (
+                "orders".to_string(),
+                vec!["id".to_string(), "amount".to_string()],
+            ),
+            (
+                "stores".to_string(),
+                vec!["store_id".to_string(), "region".to_string()],
+            ),
+        ]);
+        let lineage = column_lineage(
+            "CREATE TABLE summary AS SELECT s.region, o.amount * 1.2 AS gross, store_id, \
+             upper(region) || o.id AS label, count(*) AS n \
+             FROM orders o JOIN stores s ON o.id = s.store_id",
+            &schemas,
+        )
+        .unwrap();
+        let names = lineage
+            .iter()
+            .map(|l| l.column.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["region", "gross", "store_id", "label", "n"]);
+        assert_eq!(sources(&lineage[0]), [(Some("stores"), "region")]);
+        assert_eq!(sources(&lineage[1]), [(Some("orders"), "amount")]);
+        assert_eq!(sources(&lineage[2]), [(Some("stores"), "store_id")]);
+        assert_eq!(
+            sources(&lineage[3]),
+            [(Some("orders"), "id"), (Some("stores"), "region")]
+        );
+        assert!(lineage[4].sources.is_empty());
+    }
+
+    #[test]
+    fn expands_wildcards_from_known_schemas() {
+        let schemas = HashMap::from([("stores".to_string(), vec!["store_id".to_string()])]);
+        let lineage = column_lineage("SELECT *, name FROM stores", &schemas).unwrap();
+        assert_eq!(lineage[0].column, "store_id");
+        assert_eq!(sources(&lineage[0]), [(Some("stores"), "store_id")]);
+        assert_eq!(sources(&lineage[1]), [(Some("stores"), "name")]);
+        assert!(
+            column_lineage("DROP TABLE stores", &schemas)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
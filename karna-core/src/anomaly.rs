@@ -0,0 +1,171 @@
+//! Comparison of dataset snapshots taken before and after a refresh.
+
+use std::collections::BTreeMap;
+
+use crate::profile::ColumnStats;
+
+/// Shape of a dataset at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetSnapshot {
+    pub row_count: u64,
+    /// Stats for the key columns being watched, by column name.
+    pub columns: BTreeMap<String, ColumnStats>,
+}
+
+/// Limits beyond which a refresh is reported as anomalous.
+///
+/// Changes are relative to the previous snapshot, except the null fraction
+/// which is compared in absolute terms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyThresholds {
+    pub max_row_count_change: f64,
+    pub max_null_fraction_increase: f64,
+    pub max_distinct_count_change: f64,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self {
+            max_row_count_change: 0.5,
+            max_null_fraction_increase: 0.1,
+            max_distinct_count_change: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    RowCountChanged {
+        previous: u64,
+        current: u64,
+    },
+    ColumnMissing {
+        column: String,
+    },
+    NullFractionIncreased {
+        column: String,
+        previous: f64,
+        current: f64,
+    },
+    DistinctCountChanged {
+        column: String,
+        previous: u64,
+        current: u64,
+    },
+}
+
+/// Relative change from `previous` to `current`; growth from zero counts as 100%.
+fn relative_change(previous: u64, current: u64) -> f64 {
+    match (previous, current) {
+        (0, 0) => 0.0,
+        (0, _) => 1.0,
+        _ => (current as f64 - previous as f64).abs() / previous as f64,
+    }
+}
+
+/// Lists every way `current` deviates from `previous` beyond `thresholds`.
+pub fn detect_anomalies(
+    previous: &DatasetSnapshot,
+    current: &DatasetSnapshot,
+    thresholds: &AnomalyThresholds,
+) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+
+    if relative_change(previous.row_count, current.row_count) > thresholds.max_row_count_change {
+        anomalies.push(Anomaly::RowCountChanged {
+            previous: previous.row_count,
+            current: current.row_count,
+        });
+    }
+
+    for (column, before) in &previous.columns {
+        let Some(after) = current.columns.get(column) else {
+            anomalies.push(Anomaly::ColumnMissing {
+                column: column.clone(),
+            });
+            continue;
+        };
+        let (null_before, null_after) = (before.null_fraction(), after.null_fraction());
+        if null_after - null_before > thresholds.max_null_fraction_increase {
+            anomalies.push(Anomaly::NullFractionIncreased {
+                column: column.clone(),
+                previous: null_before,
+                current: null_after,
+            });
+        }
+        if relative_change(before.distinct_count, after.distinct_count)
+            > thresholds.max_distinct_count_change
+        {
+            anomalies.push(Anomaly::DistinctCountChanged {
+                column: column.clone(),
+                previous: before.distinct_count,
+                current: after.distinct_count,
+            });
+        }
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(row_count: u64, null_count: u64, distinct_count: u64) -> ColumnStats {
+        ColumnStats {
+            min: None,
+            max: None,
+            row_count,
+            null_count,
+            distinct_count,
+        }
+    }
+
+    fn snapshot(row_count: u64, columns: &[(&str, ColumnStats)]) -> DatasetSnapshot {
+        DatasetSnapshot {
+            row_count,
+            columns: columns
+                .iter()
+                .map(|(name, stats)| (name.to_string(), stats.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn small_drift_is_not_reported() {
+        let before = snapshot(1000, &[("id", stats(1000, 0, 1000))]);
+        let after = snapshot(1100, &[("id", stats(1100, 10, 1100))]);
+        assert!(detect_anomalies(&before, &after, &AnomalyThresholds::default()).is_empty());
+    }
+
+    #[test]
+    fn reports_truncated_export() {
+        let before = snapshot(
+            1000,
+            &[("id", stats(1000, 0, 1000)), ("region", stats(1000, 0, 8))],
+        );
+        let after = snapshot(100, &[("id", stats(100, 50, 50))]);
+        assert_eq!(
+            detect_anomalies(&before, &after, &AnomalyThresholds::default()),
+            [
+                Anomaly::RowCountChanged {
+                    previous: 1000,
+                    current: 100
+                },
+                Anomaly::NullFractionIncreased {
+                    column: "id".into(),
+                    previous: 0.0,
+                    current: 0.5
+                },
+                Anomaly::DistinctCountChanged {
+                    column: "id".into(),
+                    previous: 1000,
+                    current: 50
+                },
+                Anomaly::ColumnMissing {
+                    column: "region".into()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Automatic chart suggestions from a dataset's schema and column cardinalities.
+
+use crate::profile::{HistogramKind, histogram_sql, top_values_sql};
+use crate::schema::{ColumnInfo, TypeFamily};
+use crate::timeseries::{Aggregation, Bucket, Resample};
+
+const MAX_SUGGESTIONS: usize = 6;
+const MAX_CATEGORIES: u64 = 50;
+const TOP_CATEGORIES: u32 = 10;
+const MIN_CONTINUOUS_DISTINCT: u64 = 20;
+const HISTOGRAM_BUCKETS: u32 = 20;
+
+/// A column with its (possibly sampled) distinct value count.
+#[derive(Debug, Clone)]
+pub struct ColumnCardinality {
+    pub column: ColumnInfo,
+    pub distinct_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    Line,
+    Bar,
+    Histogram,
+}
+
+/// A ready-to-run chart: the query already produces the series to plot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartSuggestion {
+    pub kind: ChartKind,
+    pub title: String,
+    pub sql: String,
+}
+
+/// Suggests charts for `table`, most useful first.
+///
+/// The first temporal column gets a rows-over-time line, low-cardinality
+/// categorical columns get top-value bars, and continuous numeric columns
+/// get histograms.
+pub fn suggest_charts(table: &str, columns: &[ColumnCardinality]) -> Vec<ChartSuggestion> {
+    let mut suggestions = Vec::new();
+
+    if let Some(time) = columns
+        .iter()
+        .find(|c| c.column.family() == TypeFamily::Temporal)
+    {
+        let name = &time.column.name;
+        let resample = Resample {
+            timestamp_column: name.clone(),
+            value_column: name.clone(),
+            aggregation: Aggregation::Count,
+            bucket: Bucket::Day,
+        };
+        suggestions.push(ChartSuggestion {
+            kind: ChartKind::Line,
+            title: format!("Rows per day by {name}"),
+            sql: resample.sql(table),
+        });
+    }
+
+    for c in columns {
+        let name = &c.column.name;
+        match c.column.family() {
+            TypeFamily::Text | TypeFamily::Boolean
+                if (2..=MAX_CATEGORIES).contains(&c.distinct_count) =>
+            {
+                suggestions.push(ChartSuggestion {
+                    kind: ChartKind::Bar,
+                    title: format!("Top {name} values"),
+                    sql: top_values_sql(table, name, TOP_CATEGORIES),
+                });
+            }
+            TypeFamily::Numeric if c.distinct_count >= MIN_CONTINUOUS_DISTINCT => {
+                suggestions.push(ChartSuggestion {
+                    kind: ChartKind::Histogram,
+                    title: format!("Distribution of {name}"),
+                    sql: histogram_sql(table, name, HistogramKind::Numeric, HISTOGRAM_BUCKETS),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, distinct_count: u64) -> ColumnCardinality {
+        ColumnCardinality {
+            column: ColumnInfo::new(name, data_type),
+            distinct_count,
+        }
+    }
+
+    #[test]
+    fn suggests_line_bar_and_histogram() {
+        let suggestions = suggest_charts(
+            "orders",
+            &[
+                column("id", "BIGINT", 10_000),
+                column("status", "VARCHAR", 4),
+                column("email", "VARCHAR", 9_000),
+                column("placed_at", "TIMESTAMP", 9_500),
+                column("quantity", "INTEGER", 5),
+            ],
+        );
+        let kinds = suggestions
+            .iter()
+            .map(|s| (s.kind, s.title.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (ChartKind::Line, "Rows per day by placed_at"),
+                (ChartKind::Histogram, "Distribution of id"),
+                (ChartKind::Bar, "Top status values"),
+            ]
+        );
+        assert!(suggestions[0].sql.contains("time_bucket"));
+    }
+
+    #[test]
+    fn no_suggestions_for_unchartable_schema() {
+        assert!(suggest_charts("blobs", &[column("payload", "BLOB", 100)]).is_empty());
+    }
+}
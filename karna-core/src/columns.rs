@@ -0,0 +1,127 @@
+//! Column renames and display order for dataset tables.
+
+use std::collections::HashSet;
+
+use crate::error::{Error, Result};
+use crate::sql::quote_ident;
+
+/// Requested changes to a dataset's columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnPatch {
+    /// `(current name, new name)` pairs, applied in order.
+    pub renames: Vec<(String, String)>,
+    /// Display order using the new names; unlisted columns keep their relative order at the end.
+    pub order: Option<Vec<String>>,
+}
+
+/// Outcome of validating a [`ColumnPatch`] against the table's current columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnPlan {
+    pub statements: Vec<String>,
+    /// Every column, in display order, after the renames.
+    pub order: Vec<String>,
+}
+
+impl ColumnPatch {
+    /// Validates the patch and returns the `ALTER TABLE` statements and resulting order.
+    pub fn plan(&self, table: &str, columns: &[String]) -> Result<ColumnPlan> {
+        let mut current = columns.to_vec();
+        let mut statements = Vec::new();
+
+        for (from, to) in &self.renames {
+            let Some(index) = current.iter().position(|c| c == from) else {
+                return Err(Error::InvalidColumns(format!("no column named `{from}`")));
+            };
+            if to.trim().is_empty() {
+                return Err(Error::InvalidColumns("column names cannot be empty".into()));
+            }
+            if current.iter().any(|c| c.eq_ignore_ascii_case(to)) && !from.eq_ignore_ascii_case(to)
+            {
+                return Err(Error::InvalidColumns(format!(
+                    "column `{to}` already exists"
+                )));
+            }
+            statements.push(format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                quote_ident(table),
+                quote_ident(from),
+                quote_ident(to)
+            ));
+            current[index] = to.clone();
+        }
+
+        let order = match &self.order {
+            None => current,
+            Some(order) => {
+                let mut seen = HashSet::new();
+                for name in order {
+                    if !current.contains(name) {
+                        return Err(Error::InvalidColumns(format!("no column named `{name}`")));
+                    }
+                    if !seen.insert(name) {
+                        return Err(Error::InvalidColumns(format!("`{name}` listed twice")));
+                    }
+                }
+                let rest = current.iter().filter(|c| !seen.contains(c)).cloned();
+                order.iter().cloned().chain(rest).collect()
+            }
+        };
+
+        Ok(ColumnPlan { statements, order })
+    }
+}
+
+/// `SELECT` listing the table's columns in display order.
+pub fn ordered_select_sql(table: &str, order: &[String]) -> String {
+    let columns = order
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("SELECT {columns} FROM {}", quote_ident(table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns() -> Vec<String> {
+        ["id", "amt", "region"].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn renames_then_orders_by_new_names() {
+        let patch = ColumnPatch {
+            renames: vec![("amt".into(), "amount".into())],
+            order: Some(vec!["region".into(), "amount".into()]),
+        };
+        let plan = patch.plan("sales", &columns()).unwrap();
+        assert_eq!(
+            plan.statements,
+            ["ALTER TABLE \"sales\" RENAME COLUMN \"amt\" TO \"amount\""]
+        );
+        assert_eq!(plan.order, ["region", "amount", "id"]);
+        assert_eq!(
+            ordered_select_sql("sales", &plan.order),
+            "SELECT \"region\", \"amount\", \"id\" FROM \"sales\""
+        );
+    }
+
+    #[test]
+    fn rejects_collisions_and_unknown_columns() {
+        let collide = ColumnPatch {
+            renames: vec![("amt".into(), "ID".into())],
+            order: None,
+        };
+        assert!(collide.plan("sales", &columns()).is_err());
+
+        let unknown = ColumnPatch {
+            renames: vec![],
+            order: Some(vec!["missing".into()]),
+        };
+        assert!(matches!(
+            unknown.plan("sales", &columns()),
+            Err(Error::InvalidColumns(_))
+        ));
+    }
+}
@@ -0,0 +1,387 @@
+//! Conversions of formats DuckDB can't read into CSV, written to staging
+//! before the usual CSV ingest.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use scraper::{Html, Selector};
+
+use crate::error::{Error, Result};
+
+fn invalid(reason: impl std::fmt::Display) -> Error {
+    Error::InvalidIngest(reason.to_string())
+}
+
+/// Renders rows as CSV, padding short rows to the widest one.
+fn write_csv(rows: Vec<Vec<String>>) -> Result<String> {
+    let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for mut row in rows {
+        row.resize(width, String::new());
+        writer.write_record(&row).map_err(invalid)?;
+    }
+    let bytes = writer.into_inner().map_err(invalid)?;
+    String::from_utf8(bytes).map_err(invalid)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The `index`th `<table>` of an HTML page as CSV; its first row is the header.
+pub fn html_table_to_csv(html: &str, index: usize) -> Result<String> {
+    let selector = |s| Selector::parse(s).expect("static selector");
+    let document = Html::parse_document(html);
+    let table = document
+        .select(&selector("table"))
+        .nth(index)
+        .ok_or_else(|| invalid(format!("page has no table #{}", index + 1)))?;
+    let cells = selector("th, td");
+    let rows = table
+        .select(&selector("tr"))
+        .map(|row| {
+            row.select(&cells)
+                .map(|cell| collapse_whitespace(&cell.text().collect::<String>()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| !row.is_empty())
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return Err(invalid("table has no rows"));
+    }
+    write_csv(rows)
+}
+
+/// Adds `value` to the record's `column`, creating the column on first sight.
+fn push_field(columns: &mut Vec<String>, record: &mut Vec<String>, column: &str, value: &str) {
+    let index = columns.iter().position(|c| c == column).unwrap_or_else(|| {
+        columns.push(column.to_string());
+        columns.len() - 1
+    });
+    if record.len() <= index {
+        record.resize(index + 1, String::new());
+    }
+    record[index].push_str(value);
+}
+
+fn attributes(
+    element: &BytesStart,
+    columns: &mut Vec<String>,
+    record: &mut Vec<String>,
+    prefix: &str,
+) -> Result<()> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(invalid)?;
+        let name = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+        let value = attribute.unescape_value().map_err(invalid)?;
+        push_field(columns, record, &format!("{prefix}{name}"), &value);
+    }
+    Ok(())
+}
+
+/// Every `<record_tag>` element of an XML feed as a CSV row.
+///
+/// Attributes and child elements become columns, in the order they are first
+/// seen; text nested deeper than a child is folded into that child's column.
+pub fn xml_records_to_csv(xml: &str, record_tag: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut columns = Vec::new();
+    let mut records = Vec::new();
+    // Open record and how deep inside it the reader is.
+    let mut current: Option<(Vec<String>, usize)> = None;
+    let mut field = String::new();
+    loop {
+        let event = reader.read_event().map_err(invalid)?;
+        match (&event, current.as_mut()) {
+            (Event::Eof, _) => break,
+            (Event::Start(e), None) if e.local_name().as_ref() == record_tag.as_bytes() => {
+                let mut record = Vec::new();
+                attributes(e, &mut columns, &mut record, "")?;
+                current = Some((record, 0));
+            }
+            (Event::Empty(e), None) if e.local_name().as_ref() == record_tag.as_bytes() => {
+                let mut record = Vec::new();
+                attributes(e, &mut columns, &mut record, "")?;
+                records.push(record);
+            }
+            (Event::Start(e), Some((record, depth))) => {
+                if *depth == 0 {
+                    field = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    push_field(&mut columns, record, &field, "");
+                    attributes(e, &mut columns, record, &format!("{field}_"))?;
+                }
+                *depth += 1;
+            }
+            (Event::Empty(e), Some((record, 0))) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                push_field(&mut columns, record, &name, "");
+                attributes(e, &mut columns, record, &format!("{name}_"))?;
+            }
+            (Event::Text(text), Some((record, depth))) if *depth > 0 => {
+                let text = text.unescape().map_err(invalid)?;
+                push_field(&mut columns, record, &field, &text);
+            }
+            (Event::CData(text), Some((record, depth))) if *depth > 0 => {
+                push_field(&mut columns, record, &field, &String::from_utf8_lossy(text));
+            }
+            (Event::End(_), Some((_, depth))) if *depth > 0 => *depth -= 1,
+            (Event::End(_), Some(_)) => {
+                let (record, _) = current.take().expect("record is open");
+                records.push(record);
+            }
+            _ => {}
+        }
+    }
+    if records.is_empty() {
+        return Err(invalid(format!("no <{record_tag}> elements found")));
+    }
+    write_csv(std::iter::once(columns).chain(records).collect())
+}
+
+/// Column layout of a fixed-width file, in the order the fields appear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedWidthSpec {
+    /// `(name, width in characters)` pairs.
+    pub columns: Vec<(String, usize)>,
+}
+
+impl FixedWidthSpec {
+    /// Reads a spec file with one `name,width` line per column; blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut columns = Vec::new();
+        for line in spec.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, width) = line
+                .rsplit_once(',')
+                .ok_or_else(|| invalid(format!("expected `name,width`, got `{line}`")))?;
+            let width = width
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&w| w > 0)
+                .ok_or_else(|| invalid(format!("invalid width in `{line}`")))?;
+            columns.push((name.trim().to_string(), width));
+        }
+        if columns.is_empty() {
+            return Err(invalid("fixed-width spec has no columns"));
+        }
+        Ok(Self { columns })
+    }
+
+    /// Slices every non-blank line of `text` into trimmed fields, as CSV with a header.
+    pub fn to_csv(&self, text: &str) -> Result<String> {
+        let header = self.columns.iter().map(|(name, _)| name.clone()).collect();
+        let rows = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut chars = line.chars();
+                self.columns
+                    .iter()
+                    .map(|&(_, width)| {
+                        chars
+                            .by_ref()
+                            .take(width)
+                            .collect::<String>()
+                            .trim()
+                            .to_string()
+                    })
+                    .collect()
+            });
+        write_csv(std::iter::once(header).chain(rows).collect())
+    }
+}
+
+/// One sample of a Prometheus text-format snapshot.
+struct Sample {
+    metric: String,
+    labels: BTreeMap<String, String>,
+    value: String,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+/// Parses `name{label="value",...} value [timestamp_ms]`.
+fn parse_sample(line: &str) -> Result<Sample> {
+    let bad = || invalid(format!("invalid metrics line `{line}`"));
+    let name_end = line.find(['{', ' ', '\t']).ok_or_else(bad)?;
+    let metric = line[..name_end].to_string();
+    let mut rest = &line[name_end..];
+    let mut labels = BTreeMap::new();
+    if let Some(body) = rest.strip_prefix('{') {
+        let mut chars = body.char_indices().peekable();
+        loop {
+            while chars
+                .next_if(|(_, c)| c.is_whitespace() || *c == ',')
+                .is_some()
+            {}
+            let Some((start, c)) = chars.next() else {
+                return Err(bad());
+            };
+            if c == '}' {
+                rest = &body[start + 1..];
+                break;
+            }
+            let mut name = c.to_string();
+            for (_, c) in chars.by_ref() {
+                if c == '=' {
+                    break;
+                }
+                name.push(c);
+            }
+            if chars.next().map(|(_, c)| c) != Some('"') {
+                return Err(bad());
+            }
+            let mut value = String::new();
+            loop {
+                match chars.next().ok_or_else(bad)?.1 {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or_else(bad)?.1 {
+                        'n' => value.push('\n'),
+                        other => value.push(other),
+                    },
+                    c => value.push(c),
+                }
+            }
+            labels.insert(name.trim().to_string(), value);
+        }
+    }
+    let mut fields = rest.split_whitespace();
+    let value = fields.next().ok_or_else(bad)?.to_string();
+    let timestamp = match fields.next() {
+        None => None,
+        Some(ms) => Some(
+            ms.parse()
+                .ok()
+                .and_then(DateTime::from_timestamp_millis)
+                .ok_or_else(bad)?,
+        ),
+    };
+    Ok(Sample {
+        metric,
+        labels,
+        value,
+        timestamp,
+    })
+}
+
+/// A Prometheus / VictoriaMetrics text-format snapshot as long-format CSV:
+/// `metric`, `timestamp`, `value` and one column per label name.
+///
+/// Samples without their own timestamp get `scraped_at`.
+pub fn prometheus_to_csv(text: &str, scraped_at: DateTime<Utc>) -> Result<String> {
+    let samples = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_sample)
+        .collect::<Result<Vec<_>>>()?;
+    if samples.is_empty() {
+        return Err(invalid("snapshot has no samples"));
+    }
+    let labels = samples
+        .iter()
+        .flat_map(|s| s.labels.keys())
+        .filter(|name| *name != "__name__")
+        .collect::<BTreeSet<_>>();
+    let fixed = ["metric", "timestamp", "value"];
+    let header = fixed
+        .iter()
+        .map(|c| c.to_string())
+        .chain(labels.iter().map(|name| {
+            if fixed.contains(&name.as_str()) {
+                format!("label_{name}")
+            } else {
+                name.to_string()
+            }
+        }))
+        .collect();
+    let rows = samples.iter().map(|sample| {
+        let timestamp = sample.timestamp.unwrap_or(scraped_at);
+        [
+            sample.metric.clone(),
+            timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            sample.value.clone(),
+        ]
+        .into_iter()
+        .chain(
+            labels
+                .iter()
+                .map(|name| sample.labels.get(*name).cloned().unwrap_or_default()),
+        )
+        .collect()
+    });
+    write_csv(std::iter::once(header).chain(rows).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_an_html_table() {
+        let html = "<p>Intro</p><table><tr><td>skip</td></tr></table>\
+                    <table><thead><tr><th>Country</th><th>GDP,\n bn</th></tr></thead>\
+                    <tbody><tr><td>France</td><td>3 030</td></tr><tr><td>Malta</td></tr></tbody></table>";
+        assert_eq!(
+            html_table_to_csv(html, 1).unwrap(),
+            "Country,\"GDP, bn\"\nFrance,3 030\nMalta,\n"
+        );
+        assert!(html_table_to_csv(html, 2).is_err());
+    }
+
+    #[test]
+    fn converts_xml_records() {
+        let xml = r#"<?xml version="1.0"?>
+            <feed>
+              <item id="1"><title>Rust &amp; DuckDB</title><price currency="EUR">10</price></item>
+              <item id="2"><title><![CDATA[<b>Bold</b>]]></title><tags><tag>a</tag><tag>b</tag></tags></item>
+            </feed>"#;
+        assert_eq!(
+            xml_records_to_csv(xml, "item").unwrap(),
+            "id,title,price,price_currency,tags\n\
+             1,Rust & DuckDB,10,EUR,\n\
+             2,<b>Bold</b>,,,ab\n"
+        );
+        assert!(xml_records_to_csv(xml, "entry").is_err());
+    }
+
+    #[test]
+    fn slices_fixed_width_records() {
+        let spec =
+            FixedWidthSpec::parse("# mainframe export\nid,4\nname,10\n\namount,8\n").unwrap();
+        assert_eq!(spec.columns[1], ("name".to_string(), 10));
+        let text = "0001Zoë       00012.50\n\n0002Smith, J  00003.00\n0003Short";
+        assert_eq!(
+            spec.to_csv(text).unwrap(),
+            "id,name,amount\n0001,Zoë,00012.50\n0002,\"Smith, J\",00003.00\n0003,Short,\n"
+        );
+        assert!(FixedWidthSpec::parse("id,0").is_err());
+        assert!(FixedWidthSpec::parse("id").is_err());
+    }
+
+    #[test]
+    fn converts_metrics_snapshots_to_long_format() {
+        let text = "# HELP http_requests_total Requests.\n\
+                    # TYPE http_requests_total counter\n\
+                    http_requests_total{method=\"post\",path=\"/a,b\",value=\"x\"} 1027 1714564800000\n\
+                    http_requests_total{method=\"get\", note=\"say \\\"hi\\\"\"} 3\n\
+                    up 1\n";
+        let scraped_at = DateTime::from_timestamp(1714564860, 0).unwrap();
+        assert_eq!(
+            prometheus_to_csv(text, scraped_at).unwrap(),
+            "metric,timestamp,value,method,note,path,label_value\n\
+             http_requests_total,2024-05-01T12:00:00.000Z,1027,post,,\"/a,b\",x\n\
+             http_requests_total,2024-05-01T12:01:00.000Z,3,get,\"say \"\"hi\"\"\",,\n\
+             up,2024-05-01T12:01:00.000Z,1,,,,\n"
+        );
+        assert!(prometheus_to_csv("up{job=\"x\" 1", scraped_at).is_err());
+        assert!(prometheus_to_csv("# nothing\n", scraped_at).is_err());
+    }
+}
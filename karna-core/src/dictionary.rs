@@ -0,0 +1,178 @@
+//! Data dictionaries describing a dataset's columns, for sharing with people
+//! who never open karna.
+
+use crate::error::Result;
+use crate::profile::{ColumnStats, ValueCount};
+use crate::report::escape_cell;
+use crate::schema::ColumnInfo;
+
+/// Example values listed per column.
+pub const EXAMPLE_VALUES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DictionaryFormat {
+    Markdown,
+    Csv,
+}
+
+impl DictionaryFormat {
+    /// Format named by the `format` query parameter (`md` or `csv`).
+    pub fn from_param(param: &str) -> Option<Self> {
+        match param {
+            "md" | "markdown" => Some(DictionaryFormat::Markdown),
+            "csv" => Some(DictionaryFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            DictionaryFormat::Markdown => "text/markdown; charset=utf-8",
+            DictionaryFormat::Csv => "text/csv",
+        }
+    }
+}
+
+/// What is known about one column, from its metadata and latest profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictionaryEntry {
+    pub column: ColumnInfo,
+    pub description: Option<String>,
+    /// `None` when the column hasn't been profiled yet.
+    pub stats: Option<ColumnStats>,
+    /// Most frequent values, as returned by the top-values query.
+    pub top_values: Vec<ValueCount>,
+}
+
+impl DictionaryEntry {
+    fn null_percent(&self) -> String {
+        self.stats
+            .as_ref()
+            .map(|s| format!("{:.1}%", s.null_fraction() * 100.0))
+            .unwrap_or_default()
+    }
+
+    fn examples(&self) -> Vec<&str> {
+        self.top_values
+            .iter()
+            .filter_map(|v| v.value.as_deref())
+            .take(EXAMPLE_VALUES)
+            .collect()
+    }
+
+    fn fields(&self) -> [String; 5] {
+        [
+            self.column.name.clone(),
+            self.column.data_type.clone(),
+            self.description.clone().unwrap_or_default(),
+            self.null_percent(),
+            self.examples().join(", "),
+        ]
+    }
+}
+
+const HEADER: [&str; 5] = ["Column", "Type", "Description", "Null %", "Examples"];
+
+/// Renders the dictionary for `dataset` in `format`.
+pub fn render(
+    dataset: &str,
+    entries: &[DictionaryEntry],
+    format: DictionaryFormat,
+) -> Result<String> {
+    match format {
+        DictionaryFormat::Markdown => Ok(markdown(dataset, entries)),
+        DictionaryFormat::Csv => csv(entries),
+    }
+}
+
+fn markdown(dataset: &str, entries: &[DictionaryEntry]) -> String {
+    let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut out = format!("# {}\n\n", escape_cell(dataset));
+    out.push_str(&row(HEADER.map(String::from).to_vec()));
+    out.push_str(&row(vec!["---".to_string(); HEADER.len()]));
+    for entry in entries {
+        let mut cells = entry.fields().map(|f| escape_cell(&f));
+        // Column names and types read best as code.
+        for cell in &mut cells[..2] {
+            *cell = format!("`{cell}`");
+        }
+        out.push_str(&row(cells.to_vec()));
+    }
+    out
+}
+
+fn csv(entries: &[DictionaryEntry]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(HEADER).map_err(std::io::Error::from)?;
+    for entry in entries {
+        writer
+            .write_record(entry.fields())
+            .map_err(std::io::Error::from)?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<DictionaryEntry> {
+        vec![
+            DictionaryEntry {
+                column: ColumnInfo::new("region", "VARCHAR"),
+                description: Some("Sales region | territory".into()),
+                stats: Some(ColumnStats {
+                    min: None,
+                    max: None,
+                    row_count: 200,
+                    null_count: 5,
+                    distinct_count: 4,
+                }),
+                top_values: ["EU", "US", "APAC", "LATAM"]
+                    .into_iter()
+                    .map(|v| ValueCount {
+                        value: Some(v.into()),
+                        count: 10,
+                    })
+                    .chain([ValueCount {
+                        value: None,
+                        count: 5,
+                    }])
+                    .collect(),
+            },
+            DictionaryEntry {
+                column: ColumnInfo::new("amount", "DOUBLE"),
+                description: None,
+                stats: None,
+                top_values: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn renders_markdown_tables() {
+        let md = render("sales", &entries(), DictionaryFormat::Markdown).unwrap();
+        assert!(md.starts_with("# sales\n\n| Column | Type | Description | Null % | Examples |\n"));
+        assert!(md.contains(
+            "| `region` | `VARCHAR` | Sales region \\| territory | 2.5% | EU, US, APAC |\n"
+        ));
+        assert!(md.contains("| `amount` | `DOUBLE` |  |  |  |\n"));
+    }
+
+    #[test]
+    fn renders_csv() {
+        let csv = render("sales", &entries(), DictionaryFormat::Csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Column,Type,Description,Null %,Examples");
+        assert_eq!(
+            lines[1],
+            "region,VARCHAR,Sales region | territory,2.5%,\"EU, US, APAC\""
+        );
+        assert_eq!(
+            DictionaryFormat::from_param("md"),
+            Some(DictionaryFormat::Markdown)
+        );
+        assert_eq!(DictionaryFormat::from_param("pdf"), None);
+    }
+}
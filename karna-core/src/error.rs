@@ -0,0 +1,49 @@
+use sqlparser::parser::ParserError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to parse SQL: {0}")]
+    Parse(#[from] ParserError),
+    #[error("invalid policy for table `{table}`: {reason}")]
+    InvalidPolicy { table: String, reason: String },
+    #[error("table `{table}` has an access policy and can only be read by queries and COPY ... TO")]
+    PolicyViolation { table: String },
+    #[error("`{function}` can't be used in queries subject to access policies")]
+    PolicyFunction { function: String },
+    #[error("invalid transform: {0}")]
+    InvalidTransform(String),
+    #[error("invalid join: {0}")]
+    InvalidJoin(String),
+    #[error("invalid full-text index: {0}")]
+    InvalidFtsIndex(String),
+    #[error("invalid report template: {0}")]
+    InvalidTemplate(String),
+    #[error("invalid column change: {0}")]
+    InvalidColumns(String),
+    #[error("invalid models: {0}")]
+    InvalidModels(String),
+    #[error("invalid ingest options: {0}")]
+    InvalidIngest(String),
+    #[error("invalid OData query: {0}")]
+    InvalidODataQuery(String),
+    #[error("invalid source: {0}")]
+    InvalidSource(String),
+    #[error("invalid partition value: {0}")]
+    InvalidPartition(String),
+    #[error("a dataset named `{name}` already exists")]
+    DatasetExists { name: String, suggested: String },
+    #[error("invalid dataset name: {0}")]
+    InvalidDatasetName(String),
+    #[error("invalid query parameters: {0}")]
+    InvalidParameters(String),
+    #[error(
+        "dataset `{dataset}` is used by {count} other item(s); pass force=true to delete it anyway"
+    )]
+    HasDependents { dataset: String, count: usize },
+    #[error("not enough disk space: {required} bytes needed, {available} available")]
+    InsufficientStorage { required: u64, available: u64 },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,67 @@
+//! Exports written to a file by DuckDB, so results larger than memory never
+//! have to be collected into rows before being sent to the client.
+
+use std::path::Path;
+
+use crate::sql::quote_literal;
+use crate::storage::ParquetOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    fn copy_options(self) -> String {
+        match self {
+            ExportFormat::Csv => "FORMAT csv, HEADER true".to_string(),
+            ExportFormat::Parquet => ParquetOptions::default().copy_options(),
+        }
+    }
+}
+
+/// `COPY` streaming the rows of `select` into `target`, which is then served
+/// to the client as a file.
+pub fn export_sql(select: &str, target: &Path, format: ExportFormat) -> String {
+    format!(
+        "COPY ({select}) TO {} ({})",
+        quote_literal(&target.to_string_lossy()),
+        format.copy_options()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_results_to_a_file() {
+        assert_eq!(
+            export_sql(
+                "SELECT * FROM events",
+                Path::new("/tmp/karna/export.csv"),
+                ExportFormat::Csv
+            ),
+            "COPY (SELECT * FROM events) TO '/tmp/karna/export.csv' (FORMAT csv, HEADER true)"
+        );
+        assert_eq!(
+            export_sql("SELECT 1", Path::new("out.parquet"), ExportFormat::Parquet),
+            "COPY (SELECT 1) TO 'out.parquet' (FORMAT parquet, COMPRESSION zstd, ROW_GROUP_SIZE 122880)"
+        );
+    }
+}
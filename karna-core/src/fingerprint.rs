@@ -0,0 +1,139 @@
+//! Compact fingerprints of query results, stored per run so two runs of a
+//! saved query or notebook cell can be compared without keeping their rows.
+
+use crate::result::QueryResult;
+
+/// FNV-1a, chosen because its output is stable across builds and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnChecksum {
+    pub name: String,
+    /// Independent of row order, so re-sorted output still compares equal.
+    pub checksum: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultFingerprint {
+    pub row_count: u64,
+    pub columns: Vec<ColumnChecksum>,
+}
+
+impl ResultFingerprint {
+    pub fn of(result: &QueryResult) -> Self {
+        let columns = result
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| ColumnChecksum {
+                name: name.clone(),
+                checksum: result.rows.iter().fold(0u64, |sum, row| {
+                    // NULL hashes differently from every string, including "".
+                    let hash = match row.get(i).and_then(Option::as_deref) {
+                        Some(value) => fnv1a(value.as_bytes()),
+                        None => fnv1a(b"\0null"),
+                    };
+                    sum.wrapping_add(hash)
+                }),
+            })
+            .collect();
+        Self {
+            row_count: result.rows.len() as u64,
+            columns,
+        }
+    }
+}
+
+/// What changed between an earlier and a later run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FingerprintDiff {
+    /// `(before, after)` when the number of rows changed.
+    pub row_count: Option<(u64, u64)>,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    /// Columns present in both runs whose values differ.
+    pub changed_columns: Vec<String>,
+}
+
+impl FingerprintDiff {
+    pub fn is_unchanged(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub fn diff(before: &ResultFingerprint, after: &ResultFingerprint) -> FingerprintDiff {
+    let find = |fp: &ResultFingerprint, name: &str| {
+        fp.columns
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.checksum)
+    };
+    let mut diff = FingerprintDiff {
+        row_count: (before.row_count != after.row_count)
+            .then_some((before.row_count, after.row_count)),
+        ..FingerprintDiff::default()
+    };
+    for column in &after.columns {
+        match find(before, &column.name) {
+            None => diff.added_columns.push(column.name.clone()),
+            Some(checksum) if checksum != column.checksum => {
+                diff.changed_columns.push(column.name.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed_columns = before
+        .columns
+        .iter()
+        .filter(|c| find(after, &c.name).is_none())
+        .map(|c| c.name.clone())
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(columns: &[&str], rows: &[&[Option<&str>]]) -> QueryResult {
+        QueryResult {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|r| r.iter().map(|v| v.map(String::from)).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn ignores_row_order_but_not_values() {
+        let first = ResultFingerprint::of(&result(
+            &["region", "total"],
+            &[&[Some("EU"), Some("10")], &[Some("US"), None]],
+        ));
+        let reordered = ResultFingerprint::of(&result(
+            &["region", "total"],
+            &[&[Some("US"), None], &[Some("EU"), Some("10")]],
+        ));
+        assert!(diff(&first, &reordered).is_unchanged());
+
+        let refreshed = ResultFingerprint::of(&result(
+            &["region", "total", "n"],
+            &[
+                &[Some("EU"), Some("12"), Some("1")],
+                &[Some("US"), Some(""), Some("1")],
+                &[Some("APAC"), Some("3"), Some("1")],
+            ],
+        ));
+        let changes = diff(&first, &refreshed);
+        assert_eq!(changes.row_count, Some((2, 3)));
+        assert_eq!(changes.added_columns, ["n"]);
+        assert_eq!(changes.changed_columns, ["region", "total"]);
+        assert!(changes.removed_columns.is_empty());
+        assert_eq!(diff(&refreshed, &first).removed_columns, ["n"]);
+    }
+}
@@ -0,0 +1,31 @@
+//! Pretty-printing of SQL for the editor's "Format" action.
+
+use sqlformat::{FormatOptions, Indent, QueryParams};
+
+/// `sql` reindented with one clause per line and keywords uppercased.
+///
+/// Formatting never fails: text the formatter doesn't understand is passed
+/// through as it was written.
+pub fn format_sql(sql: &str) -> String {
+    let options = FormatOptions {
+        indent: Indent::Spaces(2),
+        uppercase: true,
+        lines_between_queries: 1,
+    };
+    sqlformat::format(sql, &QueryParams::None, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_clauses_onto_their_own_lines() {
+        let sql = "select region, sum(amount) as total from sales where note = 'select from' group by region; select 1";
+        assert_eq!(
+            format_sql(sql),
+            "SELECT\n  region,\n  sum(amount) AS total\nFROM\n  sales\nWHERE\n  note = 'select from'\n\
+             GROUP BY\n  region;\nSELECT\n  1"
+        );
+    }
+}
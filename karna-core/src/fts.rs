@@ -0,0 +1,95 @@
+//! Full-text search indexes over dataset text columns, backed by DuckDB's `fts` extension.
+
+use crate::error::{Error, Result};
+use crate::sql::{quote_ident, quote_literal};
+
+/// A BM25 index over some text columns of a dataset table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtsIndex {
+    pub table: String,
+    /// Column uniquely identifying each row; returned with every match.
+    pub id_column: String,
+    pub columns: Vec<String>,
+}
+
+impl FtsIndex {
+    /// Schema DuckDB creates to hold the index for a table in `main`.
+    fn index_schema(&self) -> String {
+        quote_ident(&format!("fts_main_{}", self.table))
+    }
+
+    /// Statements that load the extension and (re)build the index.
+    pub fn create_sql(&self) -> Result<Vec<String>> {
+        if self.columns.is_empty() {
+            return Err(Error::InvalidFtsIndex(
+                "at least one text column is required".into(),
+            ));
+        }
+        let columns = self
+            .columns
+            .iter()
+            .map(|c| quote_literal(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(vec![
+            "INSTALL fts".to_string(),
+            "LOAD fts".to_string(),
+            format!(
+                "PRAGMA create_fts_index({}, {}, {columns}, overwrite = 1)",
+                quote_literal(&self.table),
+                quote_literal(&self.id_column),
+            ),
+        ])
+    }
+
+    pub fn drop_sql(&self) -> String {
+        format!("PRAGMA drop_fts_index({})", quote_literal(&self.table))
+    }
+
+    /// Rows matching `query`, best match first, with a `score` column appended.
+    pub fn search_sql(&self, query: &str, limit: u32) -> String {
+        format!(
+            "SELECT * FROM (SELECT *, {}.match_bm25({}, {}) AS score FROM {}) \
+             WHERE score IS NOT NULL ORDER BY score DESC LIMIT {limit}",
+            self.index_schema(),
+            quote_ident(&self.id_column),
+            quote_literal(query),
+            quote_ident(&self.table),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> FtsIndex {
+        FtsIndex {
+            table: "tickets".into(),
+            id_column: "id".into(),
+            columns: vec!["title".into(), "body".into()],
+        }
+    }
+
+    #[test]
+    fn builds_index_over_selected_columns() {
+        assert_eq!(
+            index().create_sql().unwrap()[2],
+            "PRAGMA create_fts_index('tickets', 'id', 'title', 'body', overwrite = 1)"
+        );
+        let empty = FtsIndex {
+            columns: vec![],
+            ..index()
+        };
+        assert!(empty.create_sql().is_err());
+    }
+
+    #[test]
+    fn searches_with_bm25_and_escaped_query() {
+        assert_eq!(
+            index().search_sql("can't login", 10),
+            "SELECT * FROM (SELECT *, \"fts_main_tickets\".match_bm25(\"id\", 'can''t login') AS score \
+             FROM \"tickets\") WHERE score IS NOT NULL ORDER BY score DESC LIMIT 10"
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! GraphQL types generated from dataset schemas, giving clients typed access
+//! to rows with filtering, sorting and pagination.
+
+use std::collections::HashSet;
+
+use crate::schema::{ColumnInfo, TypeFamily};
+
+/// GraphQL scalar a column is exposed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scalar {
+    Int,
+    /// 64-bit and wider integers, which don't fit GraphQL's 32-bit `Int`.
+    BigInt,
+    Float,
+    String,
+    Boolean,
+    DateTime,
+}
+
+impl Scalar {
+    pub fn of(column: &ColumnInfo) -> Self {
+        let upper = column.data_type.trim().to_ascii_uppercase();
+        match column.family() {
+            TypeFamily::Numeric => match upper.as_str() {
+                "TINYINT" | "SMALLINT" | "INTEGER" | "INT" | "UTINYINT" | "USMALLINT" => {
+                    Scalar::Int
+                }
+                "BIGINT" | "HUGEINT" | "UINTEGER" | "UBIGINT" | "UHUGEINT" => Scalar::BigInt,
+                _ => Scalar::Float,
+            },
+            TypeFamily::Temporal => Scalar::DateTime,
+            TypeFamily::Boolean => Scalar::Boolean,
+            TypeFamily::Text | TypeFamily::Other => Scalar::String,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Scalar::Int => "Int",
+            Scalar::BigInt => "BigInt",
+            Scalar::Float => "Float",
+            Scalar::String => "String",
+            Scalar::Boolean => "Boolean",
+            Scalar::DateTime => "DateTime",
+        }
+    }
+}
+
+/// A dataset column exposed as a GraphQL field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphqlField {
+    pub name: String,
+    /// Column the field resolves to.
+    pub column: String,
+    pub scalar: Scalar,
+}
+
+/// Object type, filter and ordering inputs for one dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphqlType {
+    pub name: String,
+    /// Root `Query` field listing the dataset's rows.
+    pub query_field: String,
+    pub dataset: String,
+    pub fields: Vec<GraphqlField>,
+}
+
+/// Type names GraphQL builds in or this schema defines itself; each also
+/// reserves its `{name}Filter` input.
+const RESERVED_TYPES: &[&str] = &[
+    "Boolean",
+    "Float",
+    "ID",
+    "Int",
+    "String",
+    "BigInt",
+    "DateTime",
+    "SortDirection",
+    "Query",
+    "Mutation",
+    "Subscription",
+];
+
+/// Field names the `{Name}Filter` input uses for itself, and names that
+/// can't be values of the `{Name}Field` enum.
+const RESERVED_FIELDS: &[&str] = &["and", "or", "true", "false", "null"];
+
+fn is_reserved_type(name: &str) -> bool {
+    let base = name.strip_suffix("Filter").unwrap_or(name);
+    RESERVED_TYPES.contains(&name) || RESERVED_TYPES.contains(&base)
+}
+
+/// Names `sdl` defines for a dataset type called `name`.
+fn generated_type_names(name: &str) -> [String; 4] {
+    [
+        name.to_string(),
+        format!("{name}Filter"),
+        format!("{name}Field"),
+        format!("{name}OrderBy"),
+    ]
+}
+
+/// `base`, suffixed with `_2`, `_3`, ... until none of the names generated
+/// for it are reserved or in `taken`; those names are then added to `taken`.
+fn unique_type_name(base: String, taken: &mut HashSet<String>) -> String {
+    let mut name = base.clone();
+    let mut n = 1;
+    while generated_type_names(&name)
+        .iter()
+        .any(|g| is_reserved_type(g) || taken.contains(g))
+    {
+        n += 1;
+        name = format!("{base}_{n}");
+    }
+    taken.extend(generated_type_names(&name));
+    name
+}
+
+/// Replaces characters GraphQL names can't contain; names may not start
+/// with a digit or with `__`, which is reserved for introspection.
+fn sanitize(name: &str) -> String {
+    let mut out = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    while out.starts_with("__") {
+        out.remove(0);
+    }
+    out
+}
+
+fn pascal_case(name: &str) -> String {
+    let words = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            let first = chars.next().unwrap_or_default().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect::<String>();
+    sanitize(&words)
+}
+
+/// Types for every dataset in a schema, with names made unique across them.
+pub fn graphql_types(datasets: &[(&str, &[ColumnInfo])]) -> Vec<GraphqlType> {
+    let mut taken = HashSet::new();
+    datasets
+        .iter()
+        .map(|(dataset, columns)| GraphqlType::named(dataset, columns, &mut taken))
+        .collect()
+}
+
+impl GraphqlType {
+    /// Type for a single dataset; use [`graphql_types`] for a whole schema so
+    /// type names don't collide across datasets.
+    pub fn for_dataset(dataset: &str, columns: &[ColumnInfo]) -> Self {
+        Self::named(dataset, columns, &mut HashSet::new())
+    }
+
+    fn named(dataset: &str, columns: &[ColumnInfo], taken: &mut HashSet<String>) -> Self {
+        let name = unique_type_name(pascal_case(dataset), taken);
+        let mut seen = RESERVED_FIELDS
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<HashSet<_>>();
+        let fields = columns
+            .iter()
+            .map(|column| {
+                let base = sanitize(&column.name);
+                let mut field = base.clone();
+                let mut n = 1;
+                while !seen.insert(field.clone()) {
+                    n += 1;
+                    field = format!("{base}_{n}");
+                }
+                GraphqlField {
+                    name: field,
+                    column: column.name.clone(),
+                    scalar: Scalar::of(column),
+                }
+            })
+            .collect();
+        let mut query_field = name.clone();
+        query_field[..1].make_ascii_lowercase();
+        Self {
+            name,
+            query_field,
+            dataset: dataset.to_string(),
+            fields,
+        }
+    }
+
+    /// Column behind a field of this type, for resolving filters and sorts.
+    pub fn column(&self, field: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.name == field)
+            .map(|f| f.column.as_str())
+    }
+
+    fn sdl(&self) -> String {
+        let name = &self.name;
+        let block = |keyword: &str, type_name: String, lines: Vec<String>| {
+            format!("{keyword} {type_name} {{\n{}\n}}\n", lines.join("\n"))
+        };
+        let object = self
+            .fields
+            .iter()
+            .map(|f| format!("  {}: {}", f.name, f.scalar.name()))
+            .collect();
+        let filter = self
+            .fields
+            .iter()
+            .map(|f| format!("  {}: {}Filter", f.name, f.scalar.name()))
+            .chain([
+                format!("  and: [{name}Filter!]"),
+                format!("  or: [{name}Filter!]"),
+            ])
+            .collect();
+        let columns = self
+            .fields
+            .iter()
+            .map(|f| format!("  {}", f.name))
+            .collect();
+        [
+            block("type", name.clone(), object),
+            block("input", format!("{name}Filter"), filter),
+            block("enum", format!("{name}Field"), columns),
+            block(
+                "input",
+                format!("{name}OrderBy"),
+                vec![
+                    format!("  field: {name}Field!"),
+                    "  direction: SortDirection = ASC".to_string(),
+                ],
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+/// Comparison operators shared by every filterable scalar.
+fn scalar_filter(scalar: Scalar) -> String {
+    let name = scalar.name();
+    let mut lines = ["eq", "neq", "gt", "gte", "lt", "lte"]
+        .map(|op| format!("  {op}: {name}"))
+        .to_vec();
+    lines.push(format!("  in: [{name}!]"));
+    if scalar == Scalar::String {
+        lines.push("  contains: String".to_string());
+    }
+    lines.push("  isNull: Boolean".to_string());
+    format!("input {name}Filter {{\n{}\n}}\n", lines.join("\n"))
+}
+
+/// Full schema for `types`, regenerated whenever a dataset is created or refreshed.
+pub fn schema_sdl(types: &[GraphqlType]) -> String {
+    let mut sdl =
+        "scalar BigInt\nscalar DateTime\n\nenum SortDirection {\n  ASC\n  DESC\n}\n".to_string();
+    let mut scalars = Vec::new();
+    for field in types.iter().flat_map(|t| &t.fields) {
+        if !scalars.contains(&field.scalar) {
+            scalars.push(field.scalar);
+        }
+    }
+    for scalar in scalars {
+        sdl.push('\n');
+        sdl.push_str(&scalar_filter(scalar));
+    }
+    for t in types {
+        sdl.push('\n');
+        sdl.push_str(&t.sdl());
+    }
+    let queries = types
+        .iter()
+        .map(|t| {
+            format!(
+                "  {}(where: {name}Filter, orderBy: [{name}OrderBy!], limit: Int = 100, offset: Int = 0): [{name}!]!",
+                t.query_field,
+                name = t.name
+            )
+        })
+        .collect::<Vec<_>>();
+    sdl.push_str(&format!("\ntype Query {{\n{}\n}}\n", queries.join("\n")));
+    sdl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_columns_to_typed_fields() {
+        let columns = [
+            ColumnInfo::new("id", "BIGINT"),
+            ColumnInfo::new("order date", "DATE"),
+            ColumnInfo::new("2nd_total", "DECIMAL(18,2)"),
+            ColumnInfo::new("order-date", "VARCHAR"),
+            ColumnInfo::new("__typename", "VARCHAR"),
+        ];
+        let t = GraphqlType::for_dataset("sales_2024", &columns);
+        assert_eq!(t.name, "Sales2024");
+        assert_eq!(t.query_field, "sales2024");
+        let fields = t
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.scalar))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("id", Scalar::BigInt),
+                ("order_date", Scalar::DateTime),
+                ("_2nd_total", Scalar::Float),
+                ("order_date_2", Scalar::String),
+                ("_typename", Scalar::String),
+            ]
+        );
+        assert_eq!(t.column("order_date_2"), Some("order-date"));
+    }
+
+    /// Parses `sdl` and checks what the parser leaves to schema validation:
+    /// unique type names, unique fields and valid enum values.
+    fn assert_valid_sdl(sdl: &str) {
+        use graphql_parser::schema::{Definition, TypeDefinition, parse_schema};
+
+        let document = parse_schema::<String>(sdl).unwrap();
+        let mut types = HashSet::new();
+        for definition in &document.definitions {
+            let Definition::TypeDefinition(definition) = definition else {
+                continue;
+            };
+            let (name, members) = match definition {
+                TypeDefinition::Scalar(t) => (&t.name, vec![]),
+                TypeDefinition::Object(t) => (&t.name, t.fields.iter().map(|f| &f.name).collect()),
+                TypeDefinition::InputObject(t) => {
+                    (&t.name, t.fields.iter().map(|f| &f.name).collect())
+                }
+                TypeDefinition::Enum(t) => {
+                    for value in &t.values {
+                        assert!(
+                            !["true", "false", "null"].contains(&value.name.as_str()),
+                            "enum {} has value {}",
+                            t.name,
+                            value.name
+                        );
+                    }
+                    (&t.name, t.values.iter().map(|v| &v.name).collect())
+                }
+                other => panic!("unexpected definition {other:?}"),
+            };
+            assert!(types.insert(name.clone()), "duplicate type {name}");
+            let mut seen = HashSet::new();
+            for member in members {
+                assert!(seen.insert(member), "duplicate member {name}.{member}");
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_generated_names_unique_and_valid() {
+        let columns = [
+            ColumnInfo::new("and", "INTEGER"),
+            ColumnInfo::new("or", "VARCHAR"),
+            ColumnInfo::new("true", "BOOLEAN"),
+            ColumnInfo::new("null", "VARCHAR"),
+        ];
+        let datasets = [
+            "order_items",
+            "OrderItems",
+            "int",
+            "string_filter",
+            "query",
+            "sort direction",
+            "users",
+            "users_filter",
+        ]
+        .map(|name| (name, &columns[..]));
+        let types = graphql_types(&datasets);
+        let names = types.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "OrderItems",
+                "OrderItems_2",
+                "Int_2",
+                "StringFilter_2",
+                "Query_2",
+                "SortDirection_2",
+                "Users",
+                "UsersFilter_2",
+            ]
+        );
+        let fields = types[0]
+            .fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["and_2", "or_2", "true_2", "null_2"]);
+        assert_valid_sdl(&schema_sdl(&types));
+    }
+
+    #[test]
+    fn renders_types_inputs_and_query_fields() {
+        let t = GraphqlType::for_dataset(
+            "users",
+            &[
+                ColumnInfo::new("id", "INTEGER"),
+                ColumnInfo::new("email", "VARCHAR"),
+            ],
+        );
+        let sdl = schema_sdl(&[t]);
+        assert!(sdl.contains("type Users {\n  id: Int\n  email: String\n}"));
+        assert!(sdl.contains("input UsersFilter {\n  id: IntFilter\n  email: StringFilter\n"));
+        assert!(sdl.contains("enum UsersField {\n  id\n  email\n}"));
+        assert!(sdl.contains("  contains: String\n"));
+        assert!(!sdl.contains("input FloatFilter"));
+        assert!(sdl.contains(
+            "  users(where: UsersFilter, orderBy: [UsersOrderBy!], limit: Int = 100, offset: Int = 0): [Users!]!"
+        ));
+    }
+}
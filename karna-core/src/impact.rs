@@ -0,0 +1,245 @@
+//! Which saved SQL depends on a dataset, so deleting it can't silently break
+//! derived datasets, saved queries, notebooks or dashboards.
+
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Ident, Query, TableFactor, VisitMut, VisitorMut};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+
+use crate::analysis::{CteScopes, referenced_tables};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    DerivedDataset,
+    SavedQuery,
+    NotebookCell,
+    DashboardTile,
+}
+
+/// A stored piece of SQL that may read from datasets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlArtifact {
+    pub kind: ArtifactKind,
+    pub id: String,
+    pub name: String,
+    pub sql: String,
+}
+
+/// Artifacts that would break if a dataset were deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeletionImpact<'a> {
+    pub dependents: Vec<&'a SqlArtifact>,
+    /// Artifacts whose SQL doesn't parse, so whether they depend on the
+    /// dataset is unknown.
+    pub unparsable: Vec<&'a SqlArtifact>,
+}
+
+impl DeletionImpact<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.dependents.is_empty() && self.unparsable.is_empty()
+    }
+}
+
+pub fn deletion_impact<'a>(dataset: &str, artifacts: &'a [SqlArtifact]) -> DeletionImpact<'a> {
+    let dataset = dataset.to_lowercase();
+    let mut impact = DeletionImpact::default();
+    for artifact in artifacts {
+        match referenced_tables(&artifact.sql) {
+            Ok(tables) if tables.contains(&dataset) => impact.dependents.push(artifact),
+            Ok(_) => {}
+            Err(_) => impact.unparsable.push(artifact),
+        }
+    }
+    impact
+}
+
+/// Allows the deletion when nothing depends on the dataset or the caller
+/// acknowledged the impact with `force`.
+///
+/// Artifacts whose SQL couldn't be parsed count as dependents, since they
+/// may read from the dataset.
+pub fn confirm_deletion(dataset: &str, impact: &DeletionImpact<'_>, force: bool) -> Result<()> {
+    if force || impact.is_empty() {
+        return Ok(());
+    }
+    Err(Error::HasDependents {
+        dataset: dataset.to_string(),
+        count: impact.dependents.len() + impact.unparsable.len(),
+    })
+}
+
+/// An artifact reading from datasets that no longer exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenReference<'a> {
+    pub artifact: &'a SqlArtifact,
+    /// Lowercased names of the missing datasets.
+    pub missing: Vec<String>,
+}
+
+/// Artifacts referencing a table not in `datasets` (lowercased names), for
+/// the admin consistency check. Unparsable SQL is skipped.
+pub fn broken_references<'a>(
+    artifacts: &'a [SqlArtifact],
+    datasets: &BTreeSet<String>,
+) -> Vec<BrokenReference<'a>> {
+    artifacts
+        .iter()
+        .filter_map(|artifact| {
+            let tables = referenced_tables(&artifact.sql).ok()?;
+            let missing = tables.difference(datasets).cloned().collect::<Vec<_>>();
+            (!missing.is_empty()).then_some(BrokenReference { artifact, missing })
+        })
+        .collect()
+}
+
+struct TableRenamer<'a> {
+    from: String,
+    to: &'a str,
+    ctes: CteScopes,
+}
+
+impl VisitorMut for TableRenamer<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        self.ctes.enter(query);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        self.ctes.exit(query);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table {
+            name, args: None, ..
+        } = factor
+            && !self.ctes.resolves(name)
+            && let Some(table) = name.0.last_mut()
+            && table.value.to_lowercase() == self.from
+        {
+            *table = Ident::with_quote('"', self.to);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// `sql` with every read of dataset `from` pointed at `to`, for bulk-fixing
+/// references to a renamed or replaced dataset. References to a CTE named
+/// `from` are left alone.
+///
+/// The SQL is re-rendered from its parse tree, so original formatting and
+/// comments are not kept.
+pub fn remap_dataset(sql: &str, from: &str, to: &str) -> Result<String> {
+    let mut statements = Parser::parse_sql(&DuckDbDialect {}, sql)?;
+    let _ = statements.visit(&mut TableRenamer {
+        from: from.to_lowercase(),
+        to,
+        ctes: CteScopes::default(),
+    });
+    Ok(statements
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(kind: ArtifactKind, id: &str, sql: &str) -> SqlArtifact {
+        SqlArtifact {
+            kind,
+            id: id.into(),
+            name: id.into(),
+            sql: sql.into(),
+        }
+    }
+
+    #[test]
+    fn finds_dependents_and_requires_force() {
+        let artifacts = [
+            artifact(ArtifactKind::SavedQuery, "q1", "SELECT * FROM Sales"),
+            artifact(
+                ArtifactKind::DashboardTile,
+                "t1",
+                "SELECT region FROM stores JOIN sales USING (id)",
+            ),
+            artifact(ArtifactKind::NotebookCell, "c1", "SELECT * FROM stores"),
+            artifact(ArtifactKind::DerivedDataset, "d1", "SELEC oops"),
+        ];
+        let impact = deletion_impact("sales", &artifacts);
+        let ids = impact
+            .dependents
+            .iter()
+            .map(|a| a.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["q1", "t1"]);
+        assert_eq!(impact.unparsable[0].id, "d1");
+
+        assert!(matches!(
+            confirm_deletion("sales", &impact, false),
+            Err(Error::HasDependents { count: 3, .. })
+        ));
+        assert!(confirm_deletion("sales", &impact, true).is_ok());
+        assert!(
+            confirm_deletion("orders", &deletion_impact("orders", &artifacts[..3]), false).is_ok()
+        );
+        assert!(matches!(
+            confirm_deletion("orders", &deletion_impact("orders", &artifacts), false),
+            Err(Error::HasDependents { count: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn sees_tables_shadowed_by_ctes() {
+        let artifacts = [artifact(
+            ArtifactKind::SavedQuery,
+            "q1",
+            "WITH sales AS (SELECT * FROM sales WHERE amount > 0) SELECT * FROM sales",
+        )];
+        let impact = deletion_impact("sales", &artifacts);
+        assert_eq!(impact.dependents.len(), 1);
+        assert!(confirm_deletion("sales", &impact, false).is_err());
+    }
+
+    #[test]
+    fn lists_broken_references_and_remaps_them() {
+        let artifacts = [
+            artifact(
+                ArtifactKind::SavedQuery,
+                "q1",
+                "WITH s AS (SELECT * FROM old_sales) SELECT * FROM s JOIN stores USING (id)",
+            ),
+            artifact(ArtifactKind::NotebookCell, "c1", "SELECT * FROM stores"),
+        ];
+        let datasets = BTreeSet::from(["stores".to_string(), "sales".to_string()]);
+        let broken = broken_references(&artifacts, &datasets);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].artifact.id, "q1");
+        assert_eq!(broken[0].missing, ["old_sales"]);
+
+        assert_eq!(
+            remap_dataset(&artifacts[0].sql, "OLD_SALES", "sales").unwrap(),
+            "WITH s AS (SELECT * FROM \"sales\") SELECT * FROM s JOIN stores USING(id)"
+        );
+    }
+
+    #[test]
+    fn remaps_around_same_named_ctes() {
+        assert_eq!(
+            remap_dataset(
+                "WITH orders AS (SELECT * FROM orders WHERE paid) SELECT * FROM orders",
+                "orders",
+                "orders_v2"
+            )
+            .unwrap(),
+            "WITH orders AS (SELECT * FROM \"orders_v2\" WHERE paid) SELECT * FROM orders"
+        );
+    }
+}
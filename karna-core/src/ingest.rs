@@ -0,0 +1,436 @@
+//! `CREATE TABLE ... AS` statements that load uploaded files into DuckDB.
+
+use std::path::Path;
+
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+
+use crate::error::{Error, Result};
+use crate::naming::{unique_name, validate_name};
+use crate::schema::ColumnInfo;
+use crate::sql::{quote_ident, quote_literal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Csv,
+    Json,
+    Parquet,
+    /// Converted to CSV in staging with [`crate::convert::FixedWidthSpec`]
+    /// before ingest, so the converted file is scanned as CSV.
+    FixedWidth,
+    /// Parsed into CSV in staging with a [`crate::logs::LogPattern`], like
+    /// [`FileFormat::FixedWidth`].
+    Log,
+}
+
+impl FileFormat {
+    /// Format implied by the file extension, if it is one karna can ingest.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" | "tsv" | "txt" => Some(FileFormat::Csv),
+            "json" | "jsonl" | "ndjson" => Some(FileFormat::Json),
+            "parquet" => Some(FileFormat::Parquet),
+            "fwf" | "dat" => Some(FileFormat::FixedWidth),
+            "log" => Some(FileFormat::Log),
+            _ => None,
+        }
+    }
+
+    /// Table function scanning the file at `path`.
+    fn scan(self, path: &Path, csv: &CsvOptions, json: &JsonOptions) -> String {
+        let path = quote_literal(&path.to_string_lossy());
+        match self {
+            FileFormat::Csv | FileFormat::FixedWidth | FileFormat::Log => {
+                format!("read_csv({path}, auto_detect = true{})", csv.options())
+            }
+            FileFormat::Json => format!("read_json({path}, auto_detect = true{})", json.options()),
+            FileFormat::Parquet => format!("read_parquet({path})"),
+        }
+    }
+}
+
+/// Parsing hints for CSVs whose dates or numbers DuckDB can't sniff, such as
+/// European exports with `DD/MM/YYYY` dates and comma decimals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// strftime-style format, e.g. `%d/%m/%Y`.
+    pub date_format: Option<String>,
+    pub timestamp_format: Option<String>,
+    /// `','` or `'.'`.
+    pub decimal_separator: Option<char>,
+    /// Types to read columns as instead of sniffing them.
+    pub column_types: Vec<ColumnInfo>,
+}
+
+impl CsvOptions {
+    /// Extra `read_csv` arguments, each with a leading comma.
+    fn options(&self) -> String {
+        let mut options = String::new();
+        if let Some(format) = &self.date_format {
+            options.push_str(&format!(", dateformat = {}", quote_literal(format)));
+        }
+        if let Some(format) = &self.timestamp_format {
+            options.push_str(&format!(", timestampformat = {}", quote_literal(format)));
+        }
+        if let Some(separator) = self.decimal_separator {
+            options.push_str(&format!(
+                ", decimal_separator = {}",
+                quote_literal(&separator.to_string())
+            ));
+        }
+        if !self.column_types.is_empty() {
+            let types = self
+                .column_types
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{}: {}",
+                        quote_literal(&c.name),
+                        quote_literal(&c.data_type)
+                    )
+                })
+                .collect::<Vec<_>>();
+            options.push_str(&format!(", types = {{{}}}", types.join(", ")));
+        }
+        options
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self.decimal_separator {
+            Some(separator) if separator != ',' && separator != '.' => Err(Error::InvalidIngest(
+                format!("unsupported decimal separator `{separator}`"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How nested JSON documents are read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Nesting depth detected as structs and lists; deeper values stay `JSON`.
+    pub maximum_depth: Option<u32>,
+    /// Whether top-level objects are unpacked into columns (`true`) or kept as
+    /// a single column (`false`); `None` lets DuckDB decide.
+    pub records: Option<bool>,
+}
+
+impl JsonOptions {
+    /// Extra `read_json` arguments, each with a leading comma.
+    fn options(&self) -> String {
+        let mut options = String::new();
+        if let Some(depth) = self.maximum_depth {
+            options.push_str(&format!(", maximum_depth = {depth}"));
+        }
+        if let Some(records) = self.records {
+            options.push_str(&format!(", records = {records}"));
+        }
+        options
+    }
+}
+
+/// How one nested column is flattened after ingest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flatten {
+    /// Replace a struct column with one column per field.
+    Fields(String),
+    /// Turn each element of a list column into its own row.
+    Rows(String),
+}
+
+/// Statement flattening nested columns of `table` in place.
+///
+/// List columns unnested together are zipped, not cross-joined.
+pub fn flatten_sql(table: &str, columns: &[Flatten]) -> Result<String> {
+    if columns.is_empty() {
+        return Err(Error::InvalidIngest("no columns to flatten".into()));
+    }
+    let names = columns
+        .iter()
+        .map(|f| match f {
+            Flatten::Fields(c) | Flatten::Rows(c) => quote_ident(c),
+        })
+        .collect::<Vec<_>>();
+    let unnests = columns
+        .iter()
+        .zip(&names)
+        .map(|(f, name)| match f {
+            Flatten::Fields(_) => format!("unnest({name})"),
+            Flatten::Rows(_) => format!("unnest({name}) AS {name}"),
+        })
+        .collect::<Vec<_>>();
+    let table = quote_ident(table);
+    Ok(format!(
+        "CREATE OR REPLACE TABLE {table} AS SELECT * EXCLUDE ({}), {} FROM {table}",
+        names.join(", "),
+        unnests.join(", ")
+    ))
+}
+
+/// Upload options narrowing what gets materialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngestOptions {
+    /// Columns to keep, in order; `None` keeps every column.
+    pub columns: Option<Vec<String>>,
+    /// SQL predicate rows must satisfy, e.g. `order_date >= DATE '2024-01-01'`.
+    pub filter: Option<String>,
+    /// Used for [`FileFormat::Csv`], and for [`FileFormat::FixedWidth`] and
+    /// [`FileFormat::Log`] once converted to CSV in staging.
+    pub csv: CsvOptions,
+    /// Only used for [`FileFormat::Json`].
+    pub json: JsonOptions,
+}
+
+/// Checks that `filter` is a single SQL expression, so it can be spliced into a `WHERE`.
+fn validate_filter(filter: &str) -> Result<()> {
+    let invalid = || Error::InvalidIngest(format!("invalid row filter `{filter}`"));
+    let mut parser = Parser::new(&DuckDbDialect {})
+        .try_with_sql(filter)
+        .map_err(|_| invalid())?;
+    parser.parse_expr().map_err(|_| invalid())?;
+    if parser.peek_token().token != Token::EOF {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// What to do when an upload would create a dataset whose name is taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Drop the existing table and load the file in its place.
+    Replace,
+    /// Insert the file's rows into the existing table, matching columns by name.
+    Append,
+    /// Create the dataset under another name instead.
+    Rename(String),
+}
+
+/// Statement resolved for an upload, and the table it writes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestPlan {
+    pub table: String,
+    pub sql: String,
+}
+
+impl IngestOptions {
+    /// `SELECT` reading the selected columns and rows of `path`.
+    fn select_sql(&self, path: &Path, format: FileFormat) -> Result<String> {
+        let projection = match &self.columns {
+            None => "*".to_string(),
+            Some(columns) if columns.is_empty() => {
+                return Err(Error::InvalidIngest("no columns selected".into()));
+            }
+            Some(columns) => columns
+                .iter()
+                .map(|c| quote_ident(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        self.csv.validate()?;
+        let mut sql = format!(
+            "SELECT {projection} FROM {}",
+            format.scan(path, &self.csv, &self.json)
+        );
+        if let Some(filter) = &self.filter {
+            validate_filter(filter)?;
+            sql.push_str(&format!(" WHERE {filter}"));
+        }
+        Ok(sql)
+    }
+
+    /// Statement materializing `path` as `table`, keeping only the selected
+    /// columns and rows so the rest of the file is never stored.
+    pub fn create_table_sql(&self, table: &str, path: &Path, format: FileFormat) -> Result<String> {
+        Ok(format!(
+            "CREATE TABLE {} AS {}",
+            quote_ident(table),
+            self.select_sql(path, format)?
+        ))
+    }
+
+    /// Plans the upload of `path` as `table`, given the names of existing
+    /// datasets. Without `on_conflict`, a taken name is an
+    /// [`Error::DatasetExists`] the client resolves by picking an option.
+    pub fn plan(
+        &self,
+        table: &str,
+        path: &Path,
+        format: FileFormat,
+        existing: &[String],
+        on_conflict: Option<&OnConflict>,
+    ) -> Result<IngestPlan> {
+        let taken = |name: &str| existing.iter().any(|e| e.eq_ignore_ascii_case(name));
+        let plan = |table: &str, sql: String| IngestPlan {
+            table: table.to_string(),
+            sql,
+        };
+        if !taken(table) {
+            return Ok(plan(table, self.create_table_sql(table, path, format)?));
+        }
+        match on_conflict {
+            None => Err(Error::DatasetExists {
+                name: table.to_string(),
+                suggested: unique_name(table, existing),
+            }),
+            Some(OnConflict::Replace) => Ok(plan(
+                table,
+                format!(
+                    "CREATE OR REPLACE TABLE {} AS {}",
+                    quote_ident(table),
+                    self.select_sql(path, format)?
+                ),
+            )),
+            Some(OnConflict::Append) => Ok(plan(
+                table,
+                format!(
+                    "INSERT INTO {} BY NAME {}",
+                    quote_ident(table),
+                    self.select_sql(path, format)?
+                ),
+            )),
+            Some(OnConflict::Rename(name)) => {
+                validate_name(name, existing)?;
+                Ok(plan(name, self.create_table_sql(name, path, format)?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_columns_and_filters_rows() {
+        let options = IngestOptions {
+            columns: Some(vec!["id".into(), "order_date".into()]),
+            filter: Some("order_date >= DATE '2024-01-01'".into()),
+            ..IngestOptions::default()
+        };
+        let path = Path::new("/tmp/orders.CSV");
+        assert_eq!(
+            options
+                .create_table_sql("orders", path, FileFormat::from_path(path).unwrap())
+                .unwrap(),
+            "CREATE TABLE \"orders\" AS SELECT \"id\", \"order_date\" FROM \
+             read_csv('/tmp/orders.CSV', auto_detect = true) WHERE order_date >= DATE '2024-01-01'"
+        );
+        assert_eq!(
+            IngestOptions::default()
+                .create_table_sql("events", Path::new("e.parquet"), FileFormat::Parquet)
+                .unwrap(),
+            "CREATE TABLE \"events\" AS SELECT * FROM read_parquet('e.parquet')"
+        );
+    }
+
+    #[test]
+    fn rejects_statements_smuggled_into_the_filter() {
+        let options = IngestOptions {
+            columns: None,
+            filter: Some("true; DROP TABLE users".into()),
+            ..IngestOptions::default()
+        };
+        assert!(matches!(
+            options.create_table_sql("t", Path::new("t.csv"), FileFormat::Csv),
+            Err(Error::InvalidIngest(_))
+        ));
+    }
+
+    #[test]
+    fn passes_csv_format_hints_to_read_csv() {
+        let options = IngestOptions {
+            csv: CsvOptions {
+                date_format: Some("%d/%m/%Y".into()),
+                timestamp_format: None,
+                decimal_separator: Some(','),
+                column_types: vec![ColumnInfo::new("status", "BIGINT")],
+            },
+            ..IngestOptions::default()
+        };
+        assert_eq!(
+            options
+                .create_table_sql("sales", Path::new("ventas.csv"), FileFormat::Csv)
+                .unwrap(),
+            "CREATE TABLE \"sales\" AS SELECT * FROM read_csv('ventas.csv', auto_detect = true, \
+             dateformat = '%d/%m/%Y', decimal_separator = ',', types = {'status': 'BIGINT'})"
+        );
+
+        let bad = IngestOptions {
+            csv: CsvOptions {
+                decimal_separator: Some(';'),
+                ..CsvOptions::default()
+            },
+            ..IngestOptions::default()
+        };
+        assert!(
+            bad.create_table_sql("sales", Path::new("ventas.csv"), FileFormat::Csv)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn reads_nested_json_and_flattens_columns() {
+        let options = IngestOptions {
+            json: JsonOptions {
+                maximum_depth: Some(3),
+                records: Some(true),
+            },
+            ..IngestOptions::default()
+        };
+        assert_eq!(
+            options
+                .create_table_sql("events", Path::new("events.ndjson"), FileFormat::Json)
+                .unwrap(),
+            "CREATE TABLE \"events\" AS SELECT * FROM \
+             read_json('events.ndjson', auto_detect = true, maximum_depth = 3, records = true)"
+        );
+        assert_eq!(
+            flatten_sql(
+                "events",
+                &[Flatten::Fields("user".into()), Flatten::Rows("tags".into())]
+            )
+            .unwrap(),
+            "CREATE OR REPLACE TABLE \"events\" AS SELECT * EXCLUDE (\"user\", \"tags\"), \
+             unnest(\"user\"), unnest(\"tags\") AS \"tags\" FROM \"events\""
+        );
+        assert!(flatten_sql("events", &[]).is_err());
+    }
+
+    #[test]
+    fn resolves_name_conflicts() {
+        let options = IngestOptions::default();
+        let path = Path::new("/tmp/sales.csv");
+        let existing = ["sales".to_string()];
+        let plan = |on_conflict: Option<&OnConflict>| {
+            options.plan("sales", path, FileFormat::Csv, &existing, on_conflict)
+        };
+
+        assert!(matches!(
+            plan(None),
+            Err(Error::DatasetExists { suggested, .. }) if suggested == "sales_2"
+        ));
+        assert_eq!(
+            plan(Some(&OnConflict::Replace)).unwrap().sql,
+            "CREATE OR REPLACE TABLE \"sales\" AS SELECT * FROM \
+             read_csv('/tmp/sales.csv', auto_detect = true)"
+        );
+        assert_eq!(
+            plan(Some(&OnConflict::Append)).unwrap().sql,
+            "INSERT INTO \"sales\" BY NAME SELECT * FROM \
+             read_csv('/tmp/sales.csv', auto_detect = true)"
+        );
+        let renamed = plan(Some(&OnConflict::Rename("sales_q3".into()))).unwrap();
+        assert_eq!(renamed.table, "sales_q3");
+        assert!(renamed.sql.starts_with("CREATE TABLE \"sales_q3\" AS"));
+        assert!(plan(Some(&OnConflict::Rename("SALES".into()))).is_err());
+        assert_eq!(
+            options
+                .plan("orders", path, FileFormat::Csv, &existing, None)
+                .unwrap()
+                .table,
+            "orders"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Derived datasets built by joining two datasets without hand-written SQL.
+
+use std::collections::HashSet;
+
+use crate::error::{Error, Result};
+use crate::impact::{ArtifactKind, SqlArtifact};
+use crate::schema::{ColumnInfo, TypeFamily};
+use crate::sql::quote_ident;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+impl JoinKind {
+    fn keyword(self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+            JoinKind::Full => "FULL OUTER JOIN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A dataset table and its columns.
+#[derive(Debug, Clone)]
+pub struct JoinInput {
+    pub table: String,
+    pub columns: Vec<ColumnInfo>,
+}
+
+impl JoinInput {
+    fn column(&self, name: &str) -> Result<&ColumnInfo> {
+        self.columns
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| Error::InvalidJoin(format!("`{}` has no column `{name}`", self.table)))
+    }
+}
+
+/// A column carried into the joined dataset, optionally renamed.
+#[derive(Debug, Clone)]
+pub struct OutputColumn {
+    pub side: Side,
+    pub column: String,
+    pub alias: Option<String>,
+}
+
+impl OutputColumn {
+    fn output_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.column)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinSpec {
+    pub left: JoinInput,
+    pub right: JoinInput,
+    pub kind: JoinKind,
+    /// Pairs of `(left column, right column)` compared for equality.
+    pub keys: Vec<(String, String)>,
+    pub columns: Vec<OutputColumn>,
+}
+
+/// Whether values of the two column types can be compared in a join condition.
+fn keys_compatible(left: &ColumnInfo, right: &ColumnInfo) -> bool {
+    match (left.family(), right.family()) {
+        (TypeFamily::Other, TypeFamily::Other) => {
+            left.data_type.eq_ignore_ascii_case(&right.data_type)
+        }
+        (l, r) => l == r,
+    }
+}
+
+impl JoinSpec {
+    fn validate(&self) -> Result<()> {
+        if self.keys.is_empty() {
+            return Err(Error::InvalidJoin(
+                "at least one join key is required".into(),
+            ));
+        }
+        for (left_key, right_key) in &self.keys {
+            let (l, r) = (self.left.column(left_key)?, self.right.column(right_key)?);
+            if !keys_compatible(l, r) {
+                return Err(Error::InvalidJoin(format!(
+                    "cannot join `{}` ({}) with `{}` ({})",
+                    l.name, l.data_type, r.name, r.data_type
+                )));
+            }
+        }
+
+        if self.columns.is_empty() {
+            return Err(Error::InvalidJoin("no output columns selected".into()));
+        }
+        let mut seen = HashSet::new();
+        for output in &self.columns {
+            self.input(output.side).column(&output.column)?;
+            if !seen.insert(output.output_name()) {
+                return Err(Error::InvalidJoin(format!(
+                    "output column `{}` appears more than once; give one an alias",
+                    output.output_name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn input(&self, side: Side) -> &JoinInput {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    /// Validates the spec and renders the `SELECT` joining both datasets.
+    pub fn select_sql(&self) -> Result<String> {
+        self.validate()?;
+        let qualified = |side: Side, column: &str| {
+            let alias = match side {
+                Side::Left => "l",
+                Side::Right => "r",
+            };
+            format!("{alias}.{}", quote_ident(column))
+        };
+        let projection = self
+            .columns
+            .iter()
+            .map(|c| {
+                format!(
+                    "{} AS {}",
+                    qualified(c.side, &c.column),
+                    quote_ident(c.output_name())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let condition = self
+            .keys
+            .iter()
+            .map(|(l, r)| {
+                format!(
+                    "{} = {}",
+                    qualified(Side::Left, l),
+                    qualified(Side::Right, r)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        Ok(format!(
+            "SELECT {projection} FROM {} AS l {} {} AS r ON {condition}",
+            quote_ident(&self.left.table),
+            self.kind.keyword(),
+            quote_ident(&self.right.table),
+        ))
+    }
+
+    /// Statement materializing the join as the table `target`.
+    pub fn create_sql(&self, target: &str) -> Result<String> {
+        Ok(format!(
+            "CREATE TABLE {} AS {}",
+            quote_ident(target),
+            self.select_sql()?
+        ))
+    }
+
+    /// Datasets the joined dataset is derived from, left then right.
+    pub fn parents(&self) -> [&str; 2] {
+        [&self.left.table, &self.right.table]
+    }
+
+    /// The joined dataset `target` as a derived-dataset artifact, stored
+    /// alongside the other derived datasets so lineage and deletion impact
+    /// see both inputs as its parents.
+    pub fn artifact(&self, id: &str, target: &str) -> Result<SqlArtifact> {
+        Ok(SqlArtifact {
+            kind: ArtifactKind::DerivedDataset,
+            id: id.to_string(),
+            name: target.to_string(),
+            sql: self.select_sql()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(right_key_type: &str) -> JoinSpec {
+        JoinSpec {
+            left: JoinInput {
+                table: "orders".into(),
+                columns: vec![
+                    ColumnInfo::new("id", "BIGINT"),
+                    ColumnInfo::new("customer_id", "BIGINT"),
+                ],
+            },
+            right: JoinInput {
+                table: "customers".into(),
+                columns: vec![
+                    ColumnInfo::new("id", right_key_type),
+                    ColumnInfo::new("name", "VARCHAR"),
+                ],
+            },
+            kind: JoinKind::Left,
+            keys: vec![("customer_id".into(), "id".into())],
+            columns: vec![
+                OutputColumn {
+                    side: Side::Left,
+                    column: "id".into(),
+                    alias: Some("order_id".into()),
+                },
+                OutputColumn {
+                    side: Side::Right,
+                    column: "name".into(),
+                    alias: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn generates_join_ctas() {
+        assert_eq!(
+            spec("INTEGER").create_sql("order_customers").unwrap(),
+            "CREATE TABLE \"order_customers\" AS SELECT l.\"id\" AS \"order_id\", r.\"name\" AS \"name\" \
+             FROM \"orders\" AS l LEFT JOIN \"customers\" AS r ON l.\"customer_id\" = r.\"id\""
+        );
+    }
+
+    #[test]
+    fn records_both_inputs_as_parents() {
+        let join = spec("INTEGER");
+        assert_eq!(join.parents(), ["orders", "customers"]);
+        let artifacts = [join.artifact("d1", "order_customers").unwrap()];
+        assert_eq!(artifacts[0].kind, ArtifactKind::DerivedDataset);
+        for parent in join.parents() {
+            let impact = crate::impact::deletion_impact(parent, &artifacts);
+            assert_eq!(impact.dependents.len(), 1, "{parent}");
+        }
+        assert!(spec("VARCHAR").artifact("d2", "bad").is_err());
+    }
+
+    #[test]
+    fn rejects_incompatible_keys_and_duplicate_outputs() {
+        assert!(matches!(
+            spec("VARCHAR").select_sql(),
+            Err(Error::InvalidJoin(_))
+        ));
+
+        let mut duplicate = spec("BIGINT");
+        duplicate.columns[0].alias = None;
+        duplicate.columns.push(OutputColumn {
+            side: Side::Right,
+            column: "id".into(),
+            alias: None,
+        });
+        assert!(duplicate.select_sql().is_err());
+    }
+}
@@ -1,3 +1,44 @@
+pub mod analysis;
+pub mod anomaly;
+pub mod charts;
+pub mod columns;
+pub mod convert;
+pub mod dictionary;
+pub mod error;
+pub mod export;
+pub mod fingerprint;
+pub mod format;
+pub mod fts;
+pub mod graphql;
+pub mod impact;
+pub mod ingest;
+pub mod join;
+pub mod logs;
+pub mod models;
+pub mod naming;
+pub mod odata;
+pub mod params;
+pub mod pii;
+pub mod profile;
+pub mod remote;
+pub mod report;
+pub mod result;
+pub mod rewrite;
+pub mod scheduler;
+pub mod schema;
+pub mod script;
+pub mod settings;
+pub mod sql;
+pub mod storage;
+pub mod subscriptions;
+pub mod timeseries;
+pub mod transform;
+pub mod upload;
+pub mod usage;
+pub mod watch;
+
+pub use error::{Error, Result};
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
@@ -0,0 +1,306 @@
+//! SQL models: named `SELECT`s materialized as tables in dependency order.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::analysis::referenced_tables;
+use crate::error::{Error, Result};
+use crate::sql::{quote_ident, quote_literal};
+
+/// How a model is refreshed once it has been materialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incremental {
+    /// Insert rows newer than the stored high-watermark of `timestamp_column`.
+    Append { timestamp_column: String },
+    /// Replace rows sharing a key with the new output, then insert the rest.
+    /// With a `timestamp_column`, only rows past the watermark are considered.
+    Merge {
+        keys: Vec<String>,
+        timestamp_column: Option<String>,
+    },
+}
+
+impl Incremental {
+    fn timestamp_column(&self) -> Option<&str> {
+        match self {
+            Incremental::Append { timestamp_column } => Some(timestamp_column),
+            Incremental::Merge {
+                timestamp_column, ..
+            } => timestamp_column.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Model {
+    pub name: String,
+    pub sql: String,
+    /// `None` rebuilds the whole table on every run.
+    pub incremental: Option<Incremental>,
+}
+
+impl Model {
+    /// Rejects configurations that would silently behave like another
+    /// strategy, such as a merge without keys rebuilding the whole table.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(Incremental::Merge { keys, .. }) = &self.incremental
+            && keys.is_empty()
+        {
+            return Err(Error::InvalidModels(format!(
+                "merge model `{}` has no key columns",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Statement materializing the model's current output.
+    pub fn materialize_sql(&self) -> String {
+        format!(
+            "CREATE OR REPLACE TABLE {} AS {}",
+            quote_ident(&self.name),
+            self.sql
+        )
+    }
+
+    /// Statements refreshing the model given the watermark stored after its last run.
+    ///
+    /// Falls back to a full rebuild for non-incremental models and for the
+    /// first run of an append model. Fails for models [`Self::validate`] rejects.
+    pub fn refresh_sql(&self, watermark: Option<&str>) -> Result<Vec<String>> {
+        self.validate()?;
+        let table = quote_ident(&self.name);
+        let delta = match (
+            self.incremental.as_ref().and_then(|i| i.timestamp_column()),
+            watermark,
+        ) {
+            (Some(ts), Some(watermark)) => format!(
+                "SELECT * FROM ({}) WHERE {} > {}",
+                self.sql,
+                quote_ident(ts),
+                quote_literal(watermark)
+            ),
+            _ => self.sql.clone(),
+        };
+        Ok(match &self.incremental {
+            Some(Incremental::Append { .. }) if watermark.is_some() => {
+                vec![format!("INSERT INTO {table} {delta}")]
+            }
+            Some(Incremental::Merge { keys, .. }) => {
+                let keys = keys
+                    .iter()
+                    .map(|k| quote_ident(k))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                vec![
+                    "BEGIN TRANSACTION".to_string(),
+                    format!("CREATE OR REPLACE TEMP TABLE model_delta AS {delta}"),
+                    // First run: the delete below then empties and refills it.
+                    format!("CREATE TABLE IF NOT EXISTS {table} AS SELECT * FROM model_delta"),
+                    format!(
+                        "DELETE FROM {table} WHERE ({keys}) IN (SELECT {keys} FROM model_delta)"
+                    ),
+                    format!("INSERT INTO {table} SELECT * FROM model_delta"),
+                    "DROP TABLE model_delta".to_string(),
+                    "COMMIT".to_string(),
+                ]
+            }
+            _ => vec![self.materialize_sql()],
+        })
+    }
+
+    /// Query for the new high-watermark to store after a successful run.
+    pub fn watermark_sql(&self) -> Option<String> {
+        let ts = self.incremental.as_ref()?.timestamp_column()?;
+        Some(format!(
+            "SELECT CAST(max({}) AS VARCHAR) FROM {}",
+            quote_ident(ts),
+            quote_ident(&self.name)
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+    /// Not run because a model it depends on failed.
+    Skipped,
+}
+
+/// Orders `models` so every model runs after the models it reads from.
+///
+/// Tables that are not models (plain datasets) are treated as already
+/// available. Ties are broken by name so runs are reproducible. Every model
+/// is validated first, so a misconfigured one fails the run before it starts.
+pub fn run_order(models: &[Model]) -> Result<Vec<&Model>> {
+    for model in models {
+        model.validate()?;
+    }
+    let by_name = models
+        .iter()
+        .map(|m| (m.name.to_lowercase(), m))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut pending = BTreeMap::new();
+    for (name, model) in &by_name {
+        let upstream = referenced_tables(&model.sql)?
+            .into_iter()
+            .filter(|table| by_name.contains_key(table) && table != name)
+            .collect::<BTreeSet<_>>();
+        pending.insert(name.clone(), upstream);
+    }
+
+    let mut order = Vec::with_capacity(models.len());
+    while !pending.is_empty() {
+        let ready = pending
+            .iter()
+            .filter(|(_, upstream)| upstream.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        if ready.is_empty() {
+            let cycle = pending.keys().cloned().collect::<Vec<_>>().join(", ");
+            return Err(Error::InvalidModels(format!(
+                "dependency cycle between {cycle}"
+            )));
+        }
+        for name in ready {
+            pending.remove(&name);
+            for upstream in pending.values_mut() {
+                upstream.remove(&name);
+            }
+            order.push(by_name[&name]);
+        }
+    }
+    Ok(order)
+}
+
+/// Statuses for a run where `failed` models failed: everything downstream of them is skipped.
+pub fn propagate_failures(order: &[&Model], failed: &BTreeSet<String>) -> Result<Vec<RunStatus>> {
+    let mut broken = BTreeSet::new();
+    let mut statuses = Vec::with_capacity(order.len());
+    for model in order {
+        let name = model.name.to_lowercase();
+        let status = if failed.iter().any(|f| f.eq_ignore_ascii_case(&name)) {
+            RunStatus::Failed
+        } else if referenced_tables(&model.sql)?
+            .iter()
+            .any(|t| broken.contains(t))
+        {
+            RunStatus::Skipped
+        } else {
+            RunStatus::Succeeded
+        };
+        if status != RunStatus::Succeeded {
+            broken.insert(name);
+        }
+        statuses.push(status);
+    }
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(name: &str, sql: &str) -> Model {
+        Model {
+            name: name.into(),
+            sql: sql.into(),
+            incremental: None,
+        }
+    }
+
+    fn names<'a>(order: &[&'a Model]) -> Vec<&'a str> {
+        order.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn orders_models_after_their_dependencies() {
+        let models = [
+            model(
+                "revenue",
+                "SELECT day, sum(total) FROM clean_orders GROUP BY day",
+            ),
+            model("clean_orders", "SELECT * FROM raw_orders WHERE total > 0"),
+            model(
+                "customers_with_revenue",
+                "SELECT * FROM customers JOIN revenue USING (day)",
+            ),
+        ];
+        let order = run_order(&models).unwrap();
+        assert_eq!(
+            names(&order),
+            ["clean_orders", "revenue", "customers_with_revenue"]
+        );
+
+        let failed = BTreeSet::from(["clean_orders".to_string()]);
+        assert_eq!(
+            propagate_failures(&order, &failed).unwrap(),
+            [RunStatus::Failed, RunStatus::Skipped, RunStatus::Skipped]
+        );
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let models = [model("a", "SELECT * FROM b"), model("b", "SELECT * FROM a")];
+        assert!(matches!(run_order(&models), Err(Error::InvalidModels(_))));
+    }
+
+    #[test]
+    fn appends_rows_past_the_watermark() {
+        let events = Model {
+            incremental: Some(Incremental::Append {
+                timestamp_column: "ts".into(),
+            }),
+            ..model("events", "SELECT * FROM raw_events")
+        };
+        assert_eq!(
+            events.refresh_sql(None).unwrap(),
+            [events.materialize_sql()]
+        );
+        assert_eq!(
+            events.refresh_sql(Some("2024-05-01 00:00:00")).unwrap(),
+            [
+                "INSERT INTO \"events\" SELECT * FROM (SELECT * FROM raw_events) \
+              WHERE \"ts\" > '2024-05-01 00:00:00'"
+            ]
+        );
+        assert_eq!(
+            events.watermark_sql().unwrap(),
+            "SELECT CAST(max(\"ts\") AS VARCHAR) FROM \"events\""
+        );
+    }
+
+    #[test]
+    fn merges_by_key() {
+        let users = Model {
+            incremental: Some(Incremental::Merge {
+                keys: vec!["id".into()],
+                timestamp_column: None,
+            }),
+            ..model("users", "SELECT * FROM raw_users")
+        };
+        let sql = users.refresh_sql(None).unwrap();
+        assert_eq!(
+            sql[3],
+            "DELETE FROM \"users\" WHERE (\"id\") IN (SELECT \"id\" FROM model_delta)"
+        );
+        assert_eq!(users.watermark_sql(), None);
+    }
+
+    #[test]
+    fn rejects_merges_without_keys() {
+        let users = Model {
+            incremental: Some(Incremental::Merge {
+                keys: vec![],
+                timestamp_column: None,
+            }),
+            ..model("users", "SELECT * FROM raw_users")
+        };
+        assert!(matches!(
+            users.refresh_sql(None),
+            Err(Error::InvalidModels(_))
+        ));
+        assert!(matches!(run_order(&[users]), Err(Error::InvalidModels(_))));
+    }
+}
@@ -0,0 +1,145 @@
+//! Table names for datasets, derived from uploaded file names or chosen by
+//! the user.
+
+use std::path::Path;
+
+use deunicode::deunicode;
+
+use crate::error::{Error, Result};
+
+/// Longest name accepted in bytes, the same limit Postgres puts on identifiers.
+pub const MAX_NAME_LEN: usize = 63;
+
+/// Lowercase identifier built from a file name: the extension is dropped,
+/// non-ASCII letters are transliterated (`año` → `ano`, `销售` → `xiao_shou`)
+/// and runs of anything but letters, digits and `_` become a single `_`.
+pub fn sanitize_to_sql_name(file_name: &str) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file_name);
+    let mut name = String::new();
+    for c in deunicode(stem).chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let mut name = name.trim_matches('_').to_string();
+    if name.is_empty() {
+        name.push_str("dataset");
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert_str(0, "t_");
+    }
+    name.truncate(MAX_NAME_LEN);
+    name
+}
+
+fn is_taken(name: &str, existing: &[String]) -> bool {
+    existing.iter().any(|e| e.eq_ignore_ascii_case(name))
+}
+
+/// `base`, or `base_2`, `base_3`, ... if it is already taken.
+pub fn unique_name(base: &str, existing: &[String]) -> String {
+    if !is_taken(base, existing) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| {
+            let suffix = format!("_{n}");
+            let keep = base.len().min(MAX_NAME_LEN - suffix.len());
+            format!("{}{suffix}", &base[..base.floor_char_boundary(keep)])
+        })
+        .find(|candidate| !is_taken(candidate, existing))
+        .expect("some suffix is free")
+}
+
+/// Checks a user-chosen table name, which is used as written (quoted) in SQL
+/// and also names the dataset's storage directory and export files.
+///
+/// Beyond emptiness, surrounding whitespace, length and uniqueness, names
+/// that could be read as a path are rejected: `.`, `..`, path separators,
+/// NUL and other control characters.
+pub fn validate_name(name: &str, existing: &[String]) -> Result<()> {
+    let invalid = |reason: String| Err(Error::InvalidDatasetName(reason));
+    if name.trim().is_empty() {
+        return invalid("name cannot be empty".into());
+    }
+    if name.trim() != name {
+        return invalid("name cannot start or end with whitespace".into());
+    }
+    if matches!(name, "." | "..") {
+        return invalid(format!("`{name}` is not a valid name"));
+    }
+    if name.contains(['/', '\\']) {
+        return invalid("name cannot contain `/` or `\\`".into());
+    }
+    if name.chars().any(char::is_control) {
+        return invalid("name cannot contain control characters".into());
+    }
+    if name.len() > MAX_NAME_LEN {
+        return invalid(format!("name is longer than {MAX_NAME_LEN} bytes"));
+    }
+    if is_taken(name, existing) {
+        return invalid(format!("a dataset named `{name}` already exists"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(
+            sanitize_to_sql_name("Q3 Sales (final).csv"),
+            "q3_sales_final"
+        );
+        assert_eq!(sanitize_to_sql_name("2024-report.xlsx"), "t_2024_report");
+        assert_eq!(sanitize_to_sql_name("---.csv"), "dataset");
+        assert_eq!(sanitize_to_sql_name("ventas_año.csv"), "ventas_ano");
+        assert_eq!(
+            sanitize_to_sql_name("Übersicht Müller.csv"),
+            "ubersicht_muller"
+        );
+        assert_eq!(sanitize_to_sql_name("销售数据.csv"), "xiao_shou_shu_ju");
+        assert_eq!(sanitize_to_sql_name(&"x".repeat(100)).len(), MAX_NAME_LEN);
+    }
+
+    #[test]
+    fn suffixes_only_on_collision() {
+        let existing = ["sales".to_string(), "Sales_2".to_string()];
+        assert_eq!(unique_name("orders", &existing), "orders");
+        assert_eq!(unique_name("sales", &existing), "sales_3");
+        assert_eq!(unique_name(&"s".repeat(63), &["s".repeat(63)]).len(), 63);
+
+        assert!(validate_name("Sales Q3", &existing).is_ok());
+        assert!(validate_name(&"é".repeat(31), &existing).is_ok());
+        for bad in [
+            "",
+            " sales",
+            "SALES",
+            &"x".repeat(64),
+            &"é".repeat(32),
+            ".",
+            "..",
+            "../other",
+            "a/b",
+            "a\\b",
+            "a\0b",
+            "tab\there",
+            "line\nbreak",
+        ] {
+            assert!(
+                matches!(
+                    validate_name(bad, &existing),
+                    Err(Error::InvalidDatasetName(_))
+                ),
+                "{bad:?}"
+            );
+        }
+    }
+}
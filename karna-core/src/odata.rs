@@ -0,0 +1,535 @@
+//! OData v4 access to datasets: the `$metadata` document describing them,
+//! and translation of query options (`$select`, `$filter`, `$orderby`,
+//! `$top`, `$skip`) into a `SELECT` over a dataset.
+//!
+//! Only the subset Excel and Power BI send is supported: comparisons,
+//! `and`/`or`/`not`, date and datetime literals, and the `contains`,
+//! `startswith`, `endswith`, `tolower` and `toupper` functions.
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::error::{Error, Result};
+use crate::schema::ColumnInfo;
+use crate::sql::{quote_ident, quote_literal};
+
+/// Namespace of the entity types in the `$metadata` document.
+const NAMESPACE: &str = "Karna";
+
+/// OData identifier for a dataset or column name: characters other than
+/// letters, digits and `_` become `_`, and a leading digit gets a `_` prefix.
+pub fn odata_name(name: &str) -> String {
+    let mut out = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !out.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// [`odata_name`] of each of `names`, suffixed with `_2`, `_3`, ... where
+/// two names would otherwise map to the same identifier.
+pub fn odata_names<S: AsRef<str>>(names: &[S]) -> Vec<String> {
+    let mut taken = Vec::<String>::new();
+    for name in names {
+        let base = odata_name(name.as_ref());
+        let mut candidate = base.clone();
+        let mut n = 1;
+        while taken.contains(&candidate) {
+            n += 1;
+            candidate = format!("{base}_{n}");
+        }
+        taken.push(candidate);
+    }
+    taken
+}
+
+/// EDM type of a DuckDB column type, with its facets as XML attributes.
+fn edm_type(data_type: &str) -> (&'static str, String) {
+    let upper = data_type.trim().to_ascii_uppercase();
+    let (base, args) = match upper.split_once('(') {
+        Some((base, args)) => (base.trim(), args.trim_end_matches(')')),
+        None => (upper.as_str(), ""),
+    };
+    let edm = match base {
+        "BOOLEAN" | "BOOL" => "Edm.Boolean",
+        "TINYINT" => "Edm.SByte",
+        "UTINYINT" => "Edm.Byte",
+        "SMALLINT" => "Edm.Int16",
+        "USMALLINT" | "INTEGER" | "INT" => "Edm.Int32",
+        "UINTEGER" | "BIGINT" => "Edm.Int64",
+        "UBIGINT" | "HUGEINT" | "UHUGEINT" => {
+            return ("Edm.Decimal", r#" Precision="39" Scale="0""#.to_string());
+        }
+        "FLOAT" | "REAL" => "Edm.Single",
+        "DOUBLE" => "Edm.Double",
+        "DECIMAL" | "NUMERIC" => {
+            let mut parts = args.split(',').map(str::trim);
+            let precision = parts.next().filter(|p| !p.is_empty()).unwrap_or("18");
+            let scale = parts
+                .next()
+                .unwrap_or(if args.is_empty() { "3" } else { "0" });
+            return (
+                "Edm.Decimal",
+                format!(r#" Precision="{precision}" Scale="{scale}""#),
+            );
+        }
+        "DATE" => "Edm.Date",
+        "TIME" => "Edm.TimeOfDay",
+        "TIMESTAMP" | "DATETIME" | "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => {
+            "Edm.DateTimeOffset"
+        }
+        "UUID" => "Edm.Guid",
+        "BLOB" | "BYTEA" => "Edm.Binary",
+        _ => "Edm.String",
+    };
+    (edm, String::new())
+}
+
+/// The `$metadata` CSDL document describing `datasets` as entity sets, which
+/// Excel and Power BI request before anything else.
+///
+/// Datasets have no declared key, so the entity types have none; both tools
+/// load keyless entity sets. Entity set and property names are
+/// [`odata_names`] of the dataset and column names.
+pub fn metadata_document(datasets: &[(&str, &[ColumnInfo])]) -> String {
+    let names = datasets.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let set_names = odata_names(&names);
+    let mut types = String::new();
+    let mut sets = String::new();
+    for ((_, columns), set) in datasets.iter().zip(&set_names) {
+        types.push_str(&format!("      <EntityType Name=\"{set}\">\n"));
+        let column_names = columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        for (column, property) in columns.iter().zip(odata_names(&column_names)) {
+            let (edm, facets) = edm_type(&column.data_type);
+            types.push_str(&format!(
+                "        <Property Name=\"{property}\" Type=\"{edm}\"{facets}/>\n"
+            ));
+        }
+        types.push_str("      </EntityType>\n");
+        sets.push_str(&format!(
+            "        <EntitySet Name=\"{set}\" EntityType=\"{NAMESPACE}.{set}\"/>\n"
+        ));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<edmx:Edmx Version="4.0" xmlns:edmx="http://docs.oasis-open.org/odata/ns/edmx">
+  <edmx:DataServices>
+    <Schema Namespace="{NAMESPACE}" xmlns="http://docs.oasis-open.org/odata/ns/edm">
+{types}      <EntityContainer Name="Container">
+{sets}      </EntityContainer>
+    </Schema>
+  </edmx:DataServices>
+</edmx:Edmx>
+"#
+    )
+}
+
+/// System query options of one OData request, as raw query-string values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ODataQuery {
+    pub select: Option<String>,
+    pub filter: Option<String>,
+    pub orderby: Option<String>,
+    pub top: Option<u64>,
+    pub skip: Option<u64>,
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidODataQuery(reason.into())
+}
+
+/// Quoted column for an OData property, which must name one of `columns`
+/// either as written or by its name in the `$metadata` document.
+fn property(name: &str, columns: &[String]) -> Result<String> {
+    columns
+        .iter()
+        .find(|c| *c == name)
+        .or_else(|| {
+            let position = odata_names(columns).iter().position(|n| n == name)?;
+            columns.get(position)
+        })
+        .map(|c| quote_ident(c))
+        .ok_or_else(|| invalid(format!("unknown property `{name}`")))
+}
+
+impl ODataQuery {
+    pub fn to_sql(&self, dataset: &str, columns: &[String]) -> Result<String> {
+        let projection = match self.select.as_deref().map(str::trim) {
+            None | Some("") | Some("*") => "*".to_string(),
+            Some(select) => select
+                .split(',')
+                .map(|name| property(name.trim(), columns))
+                .collect::<Result<Vec<_>>>()?
+                .join(", "),
+        };
+        let mut sql = format!("SELECT {projection} FROM {}", quote_ident(dataset));
+        if let Some(filter) = &self.filter {
+            let mut parser = FilterParser {
+                tokens: tokenize(filter)?,
+                position: 0,
+                columns,
+            };
+            let predicate = parser.or()?;
+            if parser.position != parser.tokens.len() {
+                return Err(invalid("unexpected input at end of $filter"));
+            }
+            sql.push_str(&format!(" WHERE {predicate}"));
+        }
+        if let Some(orderby) = &self.orderby {
+            let keys = orderby
+                .split(',')
+                .map(|key| {
+                    let mut parts = key.split_whitespace();
+                    let column = property(parts.next().unwrap_or_default(), columns)?;
+                    let direction = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+                        None | Some("asc") => "ASC",
+                        Some("desc") => "DESC",
+                        Some(other) => {
+                            return Err(invalid(format!("unknown sort direction `{other}`")));
+                        }
+                    };
+                    Ok(format!("{column} {direction}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            sql.push_str(&format!(" ORDER BY {}", keys.join(", ")));
+        }
+        if let Some(top) = self.top {
+            sql.push_str(&format!(" LIMIT {top}"));
+        }
+        if let Some(skip) = self.skip {
+            sql.push_str(&format!(" OFFSET {skip}"));
+        }
+        Ok(sql)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Word(String),
+    Str(String),
+    Num(String),
+    Date(NaiveDate),
+    DateTime(DateTime<FixedOffset>),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Tok>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Tok::Open,
+                    ')' => Tok::Close,
+                    _ => Tok::Comma,
+                });
+            }
+            '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // `''` is an escaped quote inside a string literal.
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            value.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(invalid("unterminated string in $filter")),
+                    }
+                }
+                tokens.push(Tok::Str(value));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                // Numbers, and unquoted date (`2024-01-01`) and datetime
+                // (`2024-01-01T00:00:00Z`) literals.
+                let mut literal = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_digit() || matches!(c, '.' | '-' | ':' | '+' | 'T' | 'Z')) {
+                        break;
+                    }
+                    literal.push(c);
+                    chars.next();
+                }
+                let token = if let Ok(date) = NaiveDate::parse_from_str(&literal, "%Y-%m-%d") {
+                    Tok::Date(date)
+                } else if let Ok(datetime) = DateTime::parse_from_rfc3339(&literal) {
+                    Tok::DateTime(datetime)
+                } else if literal.parse::<f64>().is_ok() {
+                    Tok::Num(literal)
+                } else {
+                    return Err(invalid(format!("invalid literal `{literal}`")));
+                };
+                tokens.push(token);
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Tok::Word(word));
+            }
+            other => return Err(invalid(format!("unexpected `{other}` in $filter"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct FilterParser<'a> {
+    tokens: Vec<Tok>,
+    position: usize,
+    columns: &'a [String],
+}
+
+impl FilterParser<'_> {
+    fn peek_word(&self) -> Option<&str> {
+        match self.tokens.get(self.position) {
+            Some(Tok::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Tok> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Tok) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(invalid(format!("expected {expected:?} in $filter"))),
+        }
+    }
+
+    fn or(&mut self) -> Result<String> {
+        let mut sql = self.and()?;
+        while self.peek_word() == Some("or") {
+            self.position += 1;
+            sql = format!("({sql} OR {})", self.and()?);
+        }
+        Ok(sql)
+    }
+
+    fn and(&mut self) -> Result<String> {
+        let mut sql = self.unary()?;
+        while self.peek_word() == Some("and") {
+            self.position += 1;
+            sql = format!("({sql} AND {})", self.unary()?);
+        }
+        Ok(sql)
+    }
+
+    fn unary(&mut self) -> Result<String> {
+        if self.peek_word() == Some("not") {
+            self.position += 1;
+            return Ok(format!("(NOT {})", self.unary()?));
+        }
+        let left = self.operand()?;
+        let op = match self.peek_word() {
+            Some("eq") => "=",
+            Some("ne") => "<>",
+            Some("gt") => ">",
+            Some("ge") => ">=",
+            Some("lt") => "<",
+            Some("le") => "<=",
+            _ => return Ok(left),
+        };
+        self.position += 1;
+        let right = self.operand()?;
+        Ok(match (op, right.as_str()) {
+            ("=", "NULL") => format!("({left} IS NULL)"),
+            ("<>", "NULL") => format!("({left} IS NOT NULL)"),
+            _ => format!("({left} {op} {right})"),
+        })
+    }
+
+    fn operand(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Tok::Open) => {
+                let inner = self.or()?;
+                self.expect(Tok::Close)?;
+                Ok(inner)
+            }
+            Some(Tok::Str(value)) => Ok(quote_literal(&value)),
+            Some(Tok::Num(number)) => Ok(number),
+            Some(Tok::Date(date)) => Ok(format!("DATE '{date}'")),
+            Some(Tok::DateTime(datetime)) => Ok(format!("TIMESTAMPTZ '{}'", datetime.to_rfc3339())),
+            Some(Tok::Word(word)) => match word.as_str() {
+                "true" => Ok("TRUE".to_string()),
+                "false" => Ok("FALSE".to_string()),
+                "null" => Ok("NULL".to_string()),
+                "contains" | "startswith" | "endswith" | "tolower" | "toupper"
+                    if self.tokens.get(self.position) == Some(&Tok::Open) =>
+                {
+                    self.function(&word)
+                }
+                _ => property(&word, self.columns),
+            },
+            _ => Err(invalid("expected a value in $filter")),
+        }
+    }
+
+    fn function(&mut self, name: &str) -> Result<String> {
+        self.expect(Tok::Open)?;
+        let mut args = vec![self.operand()?];
+        while self.tokens.get(self.position) == Some(&Tok::Comma) {
+            self.position += 1;
+            args.push(self.operand()?);
+        }
+        self.expect(Tok::Close)?;
+        let (sql_name, arity) = match name {
+            "contains" => ("contains", 2),
+            "startswith" => ("starts_with", 2),
+            "endswith" => ("ends_with", 2),
+            "tolower" => ("lower", 1),
+            _ => ("upper", 1),
+        };
+        if args.len() != arity {
+            return Err(invalid(format!("{name} takes {arity} argument(s)")));
+        }
+        Ok(format!("{sql_name}({})", args.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns() -> Vec<String> {
+        ["id", "name", "price", "deleted_at"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn translates_query_options() {
+        let query = ODataQuery {
+            select: Some("id,name".into()),
+            filter: Some(
+                "price ge 10.5 and (contains(tolower(name),'o''neil') or deleted_at eq null)"
+                    .into(),
+            ),
+            orderby: Some("price desc,id".into()),
+            top: Some(50),
+            skip: Some(100),
+        };
+        assert_eq!(
+            query.to_sql("products", &columns()).unwrap(),
+            "SELECT \"id\", \"name\" FROM \"products\" WHERE ((\"price\" >= 10.5) AND \
+             (contains(lower(\"name\"), 'o''neil') OR (\"deleted_at\" IS NULL))) \
+             ORDER BY \"price\" DESC, \"id\" ASC LIMIT 50 OFFSET 100"
+        );
+        assert_eq!(
+            ODataQuery::default()
+                .to_sql("products", &columns())
+                .unwrap(),
+            "SELECT * FROM \"products\""
+        );
+    }
+
+    #[test]
+    fn translates_date_and_datetime_literals() {
+        let query = ODataQuery {
+            filter: Some(
+                "deleted_at gt 2024-01-01T00:00:00Z and deleted_at lt 2024-06-30T12:30:00.5+02:00 \
+                 or deleted_at ge 2023-12-31 and price gt -1.5"
+                    .into(),
+            ),
+            ..ODataQuery::default()
+        };
+        assert_eq!(
+            query.to_sql("products", &columns()).unwrap(),
+            "SELECT * FROM \"products\" WHERE (((\"deleted_at\" > TIMESTAMPTZ '2024-01-01T00:00:00+00:00') \
+             AND (\"deleted_at\" < TIMESTAMPTZ '2024-06-30T12:30:00.500+02:00')) \
+             OR ((\"deleted_at\" >= DATE '2023-12-31') AND (\"price\" > -1.5)))"
+        );
+    }
+
+    #[test]
+    fn describes_datasets_in_metadata() {
+        let columns = [
+            ColumnInfo::new("id", "BIGINT"),
+            ColumnInfo::new("unit price", "DECIMAL(10,2)"),
+            ColumnInfo::new("sold_on", "DATE"),
+            ColumnInfo::new("sold_at", "TIMESTAMPTZ"),
+            ColumnInfo::new("note", "VARCHAR"),
+        ];
+        let document = metadata_document(&[("2024 sales", &columns[..])]);
+        for expected in [
+            r#"<EntityType Name="_2024_sales">"#,
+            r#"<Property Name="id" Type="Edm.Int64"/>"#,
+            r#"<Property Name="unit_price" Type="Edm.Decimal" Precision="10" Scale="2"/>"#,
+            r#"<Property Name="sold_on" Type="Edm.Date"/>"#,
+            r#"<Property Name="sold_at" Type="Edm.DateTimeOffset"/>"#,
+            r#"<Property Name="note" Type="Edm.String"/>"#,
+            r#"<EntitySet Name="_2024_sales" EntityType="Karna._2024_sales"/>"#,
+        ] {
+            assert!(document.contains(expected), "{expected}\n{document}");
+        }
+
+        let mut reader = quick_xml::Reader::from_str(&document);
+        loop {
+            match reader.read_event() {
+                Ok(quick_xml::events::Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("invalid XML: {e}"),
+            }
+        }
+
+        // Clients query with the names from the document.
+        let query = ODataQuery {
+            select: Some("unit_price".into()),
+            ..ODataQuery::default()
+        };
+        let names = columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            query.to_sql("2024 sales", &names).unwrap(),
+            "SELECT \"unit price\" FROM \"2024 sales\""
+        );
+        assert_eq!(
+            odata_names(&["a b", "a_b", "a-b"]),
+            ["a_b", "a_b_2", "a_b_3"]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_properties_and_bad_syntax() {
+        let query = |filter: &str| ODataQuery {
+            filter: Some(filter.into()),
+            ..ODataQuery::default()
+        };
+        for filter in [
+            "secret eq 1",
+            "price gt",
+            "name eq 'open",
+            "price gt 1)",
+            "contains(name)",
+            "price; DROP TABLE x",
+            "deleted_at gt 2024-13-01",
+            "deleted_at gt 2024-01-01T25:00:00Z",
+        ] {
+            assert!(
+                matches!(
+                    query(filter).to_sql("products", &columns()),
+                    Err(Error::InvalidODataQuery(_))
+                ),
+                "{filter}"
+            );
+        }
+    }
+}
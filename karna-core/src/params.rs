@@ -0,0 +1,291 @@
+//! Declared parameters of saved queries, bound into the SQL as literals.
+//!
+//! A saved query refers to its parameters as `$name`; running it substitutes
+//! a typed, validated literal for each placeholder.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::error::{Error, Result};
+use crate::sql::{quote_literal, token_spans};
+
+/// Type of a parameter, which decides the form input shown for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    Text,
+    Integer,
+    Number,
+    /// `YYYY-MM-DD`.
+    Date,
+    Boolean,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryParameter {
+    pub name: String,
+    pub param_type: ParamType,
+    pub default: Option<String>,
+    /// When non-empty, the only values accepted; rendered as a dropdown.
+    pub allowed_values: Vec<String>,
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidParameters(reason.into())
+}
+
+/// Negative numbers are parenthesized: bound after a `-`, as in `5-$n`, a
+/// bare `-1` would make `--` and comment out the rest of the query.
+fn numeric_literal(number: String) -> String {
+    if number.starts_with('-') {
+        format!("({number})")
+    } else {
+        number
+    }
+}
+
+impl QueryParameter {
+    /// SQL literal for `value`, after checking it against the declared type
+    /// and allowed values.
+    pub fn literal(&self, value: &str) -> Result<String> {
+        let name = &self.name;
+        let value = value.trim();
+        if !self.allowed_values.is_empty() && !self.allowed_values.iter().any(|v| v == value) {
+            return Err(invalid(format!(
+                "`{value}` is not an allowed value for `{name}`"
+            )));
+        }
+        let mismatch =
+            |expected: &str| invalid(format!("`{name}` must be {expected}, got `{value}`"));
+        match self.param_type {
+            ParamType::Text => Ok(quote_literal(value)),
+            ParamType::Integer => value
+                .parse::<i64>()
+                .map(|n| numeric_literal(n.to_string()))
+                .map_err(|_| mismatch("an integer")),
+            ParamType::Number => match value.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(numeric_literal(n.to_string())),
+                _ => Err(mismatch("a number")),
+            },
+            ParamType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|d| format!("DATE '{d}'"))
+                .map_err(|_| mismatch("a date (YYYY-MM-DD)")),
+            ParamType::Boolean => match value.to_ascii_lowercase().as_str() {
+                "true" => Ok("TRUE".to_string()),
+                "false" => Ok("FALSE".to_string()),
+                _ => Err(mismatch("true or false")),
+            },
+        }
+    }
+}
+
+/// Checks that `sql` uses exactly the declared parameters and that every
+/// default is valid, when a saved query is created or edited.
+pub fn validate(sql: &str, parameters: &[QueryParameter]) -> Result<()> {
+    let used = placeholders(&tokenize(sql)?);
+    for name in &used {
+        if !parameters.iter().any(|p| &p.name == name) {
+            return Err(invalid(format!("`${name}` is not a declared parameter")));
+        }
+    }
+    for parameter in parameters {
+        if !used.contains(&parameter.name) {
+            return Err(invalid(format!("`{}` is never used", parameter.name)));
+        }
+        if let Some(default) = &parameter.default {
+            parameter.literal(default)?;
+        }
+    }
+    Ok(())
+}
+
+/// `sql` with each `$name` replaced by the literal for `values[name]`, or the
+/// parameter's default when no value was given.
+pub fn bind(
+    sql: &str,
+    parameters: &[QueryParameter],
+    values: &HashMap<String, String>,
+) -> Result<String> {
+    if let Some(name) = values
+        .keys()
+        .find(|k| !parameters.iter().any(|p| &p.name == *k))
+    {
+        return Err(invalid(format!("unknown parameter `{name}`")));
+    }
+    // Only placeholder spans are replaced; everything else is copied from
+    // `sql` so literals and quoted identifiers keep their escaping.
+    let mut bound = String::with_capacity(sql.len());
+    let mut copied = 0;
+    for (token, span) in token_spans(sql).map_err(|e| invalid(e.to_string()))? {
+        let Some(name) = placeholder(&token) else {
+            continue;
+        };
+        let parameter = parameters
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| invalid(format!("`${name}` is not a declared parameter")))?;
+        let value = values
+            .get(name)
+            .or(parameter.default.as_ref())
+            .ok_or_else(|| invalid(format!("missing value for `{name}`")))?;
+        bound.push_str(&sql[copied..span.start]);
+        bound.push_str(&parameter.literal(value)?);
+        copied = span.end;
+    }
+    bound.push_str(&sql[copied..]);
+    Ok(bound)
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    Tokenizer::new(&DuckDbDialect {}, sql)
+        .tokenize()
+        .map_err(|e| invalid(e.to_string()))
+}
+
+fn placeholder(token: &Token) -> Option<&str> {
+    match token {
+        Token::Placeholder(p) => p.strip_prefix('$').filter(|n| !n.is_empty()),
+        _ => None,
+    }
+}
+
+fn placeholders(tokens: &[Token]) -> Vec<String> {
+    let mut names = Vec::new();
+    for name in tokens.iter().filter_map(placeholder) {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQL: &str = "SELECT * FROM sales WHERE region = $region AND day >= $since \
+                       AND note <> '$region' LIMIT $limit";
+
+    fn parameters() -> Vec<QueryParameter> {
+        vec![
+            QueryParameter {
+                name: "region".into(),
+                param_type: ParamType::Text,
+                default: None,
+                allowed_values: vec!["EU".into(), "O'Hare".into()],
+            },
+            QueryParameter {
+                name: "since".into(),
+                param_type: ParamType::Date,
+                default: Some("2024-01-01".into()),
+                allowed_values: vec![],
+            },
+            QueryParameter {
+                name: "limit".into(),
+                param_type: ParamType::Integer,
+                default: Some("100".into()),
+                allowed_values: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn binds_values_and_defaults_as_literals() {
+        let values = HashMap::from([("region".to_string(), "O'Hare".to_string())]);
+        assert_eq!(
+            bind(SQL, &parameters(), &values).unwrap(),
+            "SELECT * FROM sales WHERE region = 'O''Hare' AND day >= DATE '2024-01-01' \
+             AND note <> '$region' LIMIT 100"
+        );
+        assert!(validate(SQL, &parameters()).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_values_and_undeclared_parameters() {
+        let bind_with = |name: &str, value: &str| {
+            bind(
+                SQL,
+                &parameters(),
+                &HashMap::from([
+                    ("region".to_string(), "EU".to_string()),
+                    (name.to_string(), value.to_string()),
+                ]),
+            )
+        };
+        for (name, value) in [
+            ("region", "US"),
+            ("limit", "10; DROP TABLE sales"),
+            ("since", "yesterday"),
+            ("other", "1"),
+        ] {
+            assert!(
+                matches!(bind_with(name, value), Err(Error::InvalidParameters(_))),
+                "{name}={value}"
+            );
+        }
+        assert!(bind(SQL, &parameters(), &HashMap::new()).is_err());
+        assert!(validate("SELECT $region, $nope", &parameters()).is_err());
+        assert!(validate("SELECT $region, $since", &parameters()).is_err());
+    }
+
+    #[test]
+    fn parenthesizes_negative_numbers() {
+        let parameters = [
+            QueryParameter {
+                name: "n".into(),
+                param_type: ParamType::Integer,
+                default: None,
+                allowed_values: vec![],
+            },
+            QueryParameter {
+                name: "x".into(),
+                param_type: ParamType::Number,
+                default: None,
+                allowed_values: vec![],
+            },
+        ];
+        let values = HashMap::from([
+            ("n".to_string(), " -1 ".to_string()),
+            ("x".to_string(), "-2.5".to_string()),
+        ]);
+        assert_eq!(
+            bind(
+                "SELECT * FROM t WHERE a = 5-$n AND b = 1-$x",
+                &parameters,
+                &values
+            )
+            .unwrap(),
+            "SELECT * FROM t WHERE a = 5-(-1) AND b = 1-(-2.5)"
+        );
+    }
+
+    #[test]
+    fn trims_values_before_checking_allowed_values() {
+        let region = &parameters()[0];
+        assert_eq!(region.literal(" EU ").unwrap(), "'EU'");
+        assert!(region.literal(" US ").is_err());
+    }
+
+    #[test]
+    fn keeps_escaped_quotes_outside_placeholders() {
+        let values = HashMap::from([("region".to_string(), "EU".to_string())]);
+        for (sql, expected) in [
+            (
+                "SELECT * FROM t WHERE note = 'O''Hare'' OR 1=1 --' AND region = $region",
+                "SELECT * FROM t WHERE note = 'O''Hare'' OR 1=1 --' AND region = 'EU'",
+            ),
+            (
+                "SELECT \"a\"\"$region\" FROM t WHERE region = $region",
+                "SELECT \"a\"\"$region\" FROM t WHERE region = 'EU'",
+            ),
+            (
+                "SELECT E'it\\'s $region' AS s, $region AS r",
+                "SELECT E'it\\'s $region' AS s, 'EU' AS r",
+            ),
+        ] {
+            assert_eq!(bind(sql, &parameters(), &values).unwrap(), expected);
+        }
+    }
+}
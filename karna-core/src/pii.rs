@@ -0,0 +1,246 @@
+//! Detection of columns likely to hold personal data, from a sample of their
+//! values and from their names.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::rewrite::{Mask, TablePolicy};
+use crate::sql::quote_ident;
+
+/// Rows sampled from a new dataset for detection.
+pub const PII_SAMPLE_ROWS: u32 = 1000;
+
+/// Share of a sample's non-null values that must match for a column to be flagged.
+const MATCH_THRESHOLD: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CreditCard,
+    IpAddress,
+    /// Only recognized by column name: national IDs, names, addresses, birth dates.
+    Personal,
+}
+
+impl PiiKind {
+    /// Masking rule applied when masking is switched on for detected columns.
+    pub fn mask(self) -> Mask {
+        match self {
+            // Hashed so the columns can still be joined and counted.
+            PiiKind::Email | PiiKind::Phone | PiiKind::IpAddress => Mask::Hash,
+            PiiKind::CreditCard | PiiKind::Personal => Mask::Redact,
+        }
+    }
+}
+
+/// A column flagged as likely personal data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiiFinding {
+    pub column: String,
+    pub kind: PiiKind,
+    /// Share of sampled non-null values that matched; `None` when flagged by name alone.
+    pub match_fraction: Option<f64>,
+}
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[\w.+-]+@[\w-]+(\.[\w-]+)*\.[A-Za-z]{2,}$").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\+?[\d\s().-]{7,20}$").unwrap());
+static IPV4: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(25[0-5]|2[0-4]\d|1?\d?\d)(\.(25[0-5]|2[0-4]\d|1?\d?\d)){3}$").unwrap()
+});
+
+/// Whether `value` is a 13–19 digit number passing the Luhn checksum.
+fn is_card_number(value: &str) -> bool {
+    let digits = value
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-'))
+        .map(|c| c.to_digit(10))
+        .collect::<Option<Vec<_>>>();
+    let Some(digits) = digits else {
+        return false;
+    };
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn matches(kind: PiiKind, value: &str) -> bool {
+    match kind {
+        PiiKind::Email => EMAIL.is_match(value),
+        PiiKind::Phone => {
+            // E.164 numbers have at most 15 digits; longer runs are IDs.
+            let digits = value.chars().filter(char::is_ascii_digit).count();
+            PHONE.is_match(value) && (7..=15).contains(&digits)
+        }
+        PiiKind::CreditCard => is_card_number(value),
+        PiiKind::IpAddress => IPV4.is_match(value) || value.parse::<std::net::Ipv6Addr>().is_ok(),
+        PiiKind::Personal => false,
+    }
+}
+
+/// Kind suggested by a column name alone.
+fn kind_from_name(column: &str) -> Option<PiiKind> {
+    let name = column.to_ascii_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+    if has(&["email", "e_mail"]) {
+        Some(PiiKind::Email)
+    } else if has(&["phone", "mobile", "msisdn"]) {
+        Some(PiiKind::Phone)
+    } else if has(&["card_number", "credit_card", "cc_number"]) {
+        Some(PiiKind::CreditCard)
+    } else if has(&["ip_address", "ip_addr", "client_ip", "remote_addr"]) {
+        Some(PiiKind::IpAddress)
+    } else if has(&[
+        "ssn",
+        "passport",
+        "national_id",
+        "tax_id",
+        "first_name",
+        "last_name",
+        "full_name",
+        "surname",
+        "address",
+        "birth",
+        "dob",
+    ]) {
+        Some(PiiKind::Personal)
+    } else {
+        None
+    }
+}
+
+/// Flags `column` from its sampled values, falling back to its name.
+pub fn detect(column: &str, sample: &[Option<String>]) -> Option<PiiFinding> {
+    let values = sample
+        .iter()
+        .flatten()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    // Card numbers first: their digits would also pass as phone numbers.
+    let by_value = if values.is_empty() {
+        None
+    } else {
+        [
+            PiiKind::CreditCard,
+            PiiKind::Email,
+            PiiKind::IpAddress,
+            PiiKind::Phone,
+        ]
+        .into_iter()
+        .map(|kind| {
+            let hits = values.iter().filter(|v| matches(kind, v)).count();
+            (kind, hits as f64 / values.len() as f64)
+        })
+        .find(|(_, fraction)| *fraction >= MATCH_THRESHOLD)
+    };
+    match by_value {
+        Some((kind, fraction)) => Some(PiiFinding {
+            column: column.to_string(),
+            kind,
+            match_fraction: Some(fraction),
+        }),
+        None => kind_from_name(column).map(|kind| PiiFinding {
+            column: column.to_string(),
+            kind,
+            match_fraction: None,
+        }),
+    }
+}
+
+/// Query sampling rows of `table` to run detection on.
+pub fn sample_sql(table: &str) -> String {
+    format!(
+        "SELECT * FROM {} USING SAMPLE {PII_SAMPLE_ROWS} ROWS",
+        quote_ident(table)
+    )
+}
+
+/// Policy masking every flagged column, for datasets where auto-masking is on.
+pub fn masking_policy(findings: &[PiiFinding]) -> TablePolicy {
+    TablePolicy {
+        row_filter: None,
+        masks: findings
+            .iter()
+            .map(|f| (f.column.clone(), f.kind.mask()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(values: &[&str]) -> Vec<Option<String>> {
+        values
+            .iter()
+            .map(|v| Some(v.to_string()))
+            .chain([None])
+            .collect()
+    }
+
+    #[test]
+    fn flags_columns_by_value_and_by_name() {
+        let contact = detect(
+            "contact",
+            &sample(&[
+                "ana@example.com",
+                "bo@mail.co.uk",
+                "n/a",
+                "c.d+x@corp.io",
+                "e@f.org",
+            ]),
+        )
+        .unwrap();
+        assert_eq!(contact.kind, PiiKind::Email);
+        assert_eq!(contact.match_fraction, Some(0.8));
+
+        let cards = detect(
+            "payment",
+            &sample(&["4111 1111 1111 1111", "5500-0000-0000-0004"]),
+        );
+        assert_eq!(cards.unwrap().kind, PiiKind::CreditCard);
+        assert_eq!(
+            detect("src", &sample(&["10.0.0.1", "::1"])).unwrap().kind,
+            PiiKind::IpAddress
+        );
+        assert_eq!(
+            detect("tel", &sample(&["+34 600 123 456", "(555) 010-9999"]))
+                .unwrap()
+                .kind,
+            PiiKind::Phone
+        );
+
+        let by_name = detect("customer_last_name", &sample(&["García"])).unwrap();
+        assert_eq!(by_name.kind, PiiKind::Personal);
+        assert_eq!(by_name.match_fraction, None);
+
+        assert_eq!(detect("amount", &sample(&["10", "12.5"])), None);
+        assert_eq!(detect("order_id", &sample(&["4111111111111112"])), None);
+    }
+
+    #[test]
+    fn builds_a_masking_policy_for_findings() {
+        let findings = [detect("email", &[]).unwrap(), detect("ssn", &[]).unwrap()];
+        let policy = masking_policy(&findings);
+        assert_eq!(policy.masks["email"], Mask::Hash);
+        assert_eq!(policy.masks["ssn"], Mask::Redact);
+        assert_eq!(
+            sample_sql("users"),
+            "SELECT * FROM \"users\" USING SAMPLE 1000 ROWS"
+        );
+    }
+}
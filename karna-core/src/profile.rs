@@ -0,0 +1,230 @@
+//! Column profiling queries.
+
+use crate::result::QueryResult;
+use crate::schema::{ColumnInfo, TypeFamily};
+use crate::sql::quote_ident;
+
+/// Summary statistics for a single column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnStats {
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub row_count: u64,
+    pub null_count: u64,
+    pub distinct_count: u64,
+}
+
+impl ColumnStats {
+    pub fn null_fraction(&self) -> f64 {
+        if self.row_count == 0 {
+            return 0.0;
+        }
+        self.null_count as f64 / self.row_count as f64
+    }
+}
+
+/// Query producing one row with the columns of [`ColumnStats`].
+///
+/// `min` and `max` are cast to `VARCHAR` so every column type yields the same
+/// result shape; the distinct count is approximate to stay cheap on large tables.
+pub fn column_stats_sql(table: &str, column: &str) -> String {
+    let table = quote_ident(table);
+    let column = quote_ident(column);
+    format!(
+        "SELECT CAST(min({column}) AS VARCHAR) AS min, \
+         CAST(max({column}) AS VARCHAR) AS max, \
+         count(*) AS row_count, \
+         count(*) - count({column}) AS null_count, \
+         approx_count_distinct({column}) AS distinct_count \
+         FROM {table}"
+    )
+}
+
+/// Whether values of `column`'s type can plausibly identify rows; floating
+/// point and boolean columns never make useful keys.
+fn is_key_candidate(column: &ColumnInfo) -> bool {
+    let upper = column.data_type.trim().to_ascii_uppercase();
+    match column.family() {
+        TypeFamily::Text | TypeFamily::Temporal => true,
+        TypeFamily::Numeric => !["FLOAT", "REAL", "DOUBLE"].contains(&upper.as_str()),
+        TypeFamily::Other => upper == "UUID",
+        TypeFamily::Boolean => false,
+    }
+}
+
+/// Query producing one row with a boolean per candidate column, named after
+/// it, that is true when the column is non-null and unique across a non-empty
+/// table. `None` when no column has a key-like type.
+///
+/// Counts are exact, unlike [`column_stats_sql`], since an approximate
+/// distinct count would misreport near-unique columns as keys.
+pub fn key_probe_sql(table: &str, columns: &[ColumnInfo]) -> Option<String> {
+    let probes = columns
+        .iter()
+        .filter(|c| is_key_candidate(c))
+        .map(|c| {
+            let column = quote_ident(&c.name);
+            format!(
+                "count(*) > 0 AND count({column}) = count(*) \
+                 AND count(DISTINCT {column}) = count(*) AS {column}"
+            )
+        })
+        .collect::<Vec<_>>();
+    if probes.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "SELECT {} FROM {}",
+        probes.join(", "),
+        quote_ident(table)
+    ))
+}
+
+/// Columns reported as keys by the result of [`key_probe_sql`].
+pub fn detected_keys(probe: &QueryResult) -> Vec<String> {
+    let Some(row) = probe.rows.first() else {
+        return Vec::new();
+    };
+    probe
+        .columns
+        .iter()
+        .zip(row)
+        .filter(|(_, value)| value.as_deref() == Some("true"))
+        .map(|(column, _)| column.clone())
+        .collect()
+}
+
+/// Column families that can be bucketed into a histogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramKind {
+    Numeric,
+    /// DATE and TIMESTAMP columns, bucketed on epoch seconds.
+    Temporal,
+}
+
+/// One equi-width histogram bucket; bounds are epoch seconds for temporal columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistogramBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u64,
+}
+
+/// Frequency of one value of a categorical column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueCount {
+    pub value: Option<String>,
+    pub count: u64,
+}
+
+/// Query producing `lower`, `upper` and `count` rows for `buckets` equal-width
+/// buckets between the column's min and max, ordered by bucket.
+///
+/// NULLs are excluded, empty buckets are omitted and the max value falls into
+/// the last bucket.
+pub fn histogram_sql(table: &str, column: &str, kind: HistogramKind, buckets: u32) -> String {
+    let table = quote_ident(table);
+    let column = quote_ident(column);
+    let buckets = buckets.max(1);
+    let value = match kind {
+        HistogramKind::Numeric => format!("CAST({column} AS DOUBLE)"),
+        HistogramKind::Temporal => format!("epoch({column})"),
+    };
+    format!(
+        "WITH vals AS (SELECT {value} AS v FROM {table} WHERE {column} IS NOT NULL), \
+         bounds AS (SELECT min(v) AS lo, (max(v) - min(v)) / {buckets} AS width FROM vals), \
+         bucketed AS (SELECT least(coalesce(CAST(floor((v - lo) / nullif(width, 0)) AS BIGINT), 0), {last}) AS bucket \
+         FROM vals, bounds) \
+         SELECT lo + bucket * width AS lower, lo + (bucket + 1) * width AS upper, count(*) AS count \
+         FROM bucketed, bounds GROUP BY bucket, lo, width ORDER BY bucket",
+        last = buckets - 1,
+    )
+}
+
+/// Query producing the `k` most frequent values as `value`, `count` rows.
+pub fn top_values_sql(table: &str, column: &str, k: u32) -> String {
+    let table = quote_ident(table);
+    let column = quote_ident(column);
+    format!(
+        "SELECT CAST({column} AS VARCHAR) AS value, count(*) AS count FROM {table} \
+         GROUP BY {column} ORDER BY count DESC, value NULLS LAST LIMIT {k}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_stats_query_with_quoted_names() {
+        assert_eq!(
+            column_stats_sql("sales", "unit price"),
+            "SELECT CAST(min(\"unit price\") AS VARCHAR) AS min, \
+             CAST(max(\"unit price\") AS VARCHAR) AS max, \
+             count(*) AS row_count, \
+             count(*) - count(\"unit price\") AS null_count, \
+             approx_count_distinct(\"unit price\") AS distinct_count \
+             FROM \"sales\""
+        );
+    }
+
+    #[test]
+    fn histogram_buckets_temporal_columns_on_epoch() {
+        let sql = histogram_sql("events", "ts", HistogramKind::Temporal, 10);
+        assert!(sql.starts_with(
+            "WITH vals AS (SELECT epoch(\"ts\") AS v FROM \"events\" WHERE \"ts\" IS NOT NULL)"
+        ));
+        assert!(sql.contains("/ 10 AS width"));
+        assert!(sql.contains(", 9) AS bucket"));
+    }
+
+    #[test]
+    fn top_values_orders_by_frequency() {
+        assert_eq!(
+            top_values_sql("sales", "region", 5),
+            "SELECT CAST(\"region\" AS VARCHAR) AS value, count(*) AS count FROM \"sales\" \
+             GROUP BY \"region\" ORDER BY count DESC, value NULLS LAST LIMIT 5"
+        );
+    }
+
+    #[test]
+    fn null_fraction_handles_empty_tables() {
+        let stats = ColumnStats {
+            min: None,
+            max: None,
+            row_count: 0,
+            null_count: 0,
+            distinct_count: 0,
+        };
+        assert_eq!(stats.null_fraction(), 0.0);
+        let stats = ColumnStats {
+            row_count: 4,
+            null_count: 1,
+            ..stats
+        };
+        assert_eq!(stats.null_fraction(), 0.25);
+    }
+
+    #[test]
+    fn probes_key_like_columns_and_reads_back_keys() {
+        let columns = [
+            ColumnInfo::new("id", "BIGINT"),
+            ColumnInfo::new("score", "DOUBLE"),
+            ColumnInfo::new("active", "BOOLEAN"),
+            ColumnInfo::new("email", "VARCHAR"),
+        ];
+        assert_eq!(
+            key_probe_sql("users", &columns).unwrap(),
+            "SELECT count(*) > 0 AND count(\"id\") = count(*) AND count(DISTINCT \"id\") = count(*) AS \"id\", \
+             count(*) > 0 AND count(\"email\") = count(*) AND count(DISTINCT \"email\") = count(*) AS \"email\" \
+             FROM \"users\""
+        );
+        assert_eq!(key_probe_sql("users", &columns[1..3]), None);
+
+        let probe = QueryResult {
+            columns: vec!["id".into(), "email".into()],
+            rows: vec![vec![Some("true".into()), Some("false".into())]],
+        };
+        assert_eq!(detected_keys(&probe), ["id"]);
+    }
+}
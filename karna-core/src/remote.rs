@@ -0,0 +1,182 @@
+//! Object-store locations (S3, GCS, Azure Blob) that datasets are read from.
+
+use crate::error::{Error, Result};
+use crate::sql::{quote_ident, quote_literal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    S3,
+    Gcs,
+    Azure,
+}
+
+impl Provider {
+    fn secret_type(self) -> &'static str {
+        match self {
+            Provider::S3 => "s3",
+            Provider::Gcs => "gcs",
+            Provider::Azure => "azure",
+        }
+    }
+
+    /// DuckDB extension that reads this provider's URIs.
+    fn extension(self) -> &'static str {
+        match self {
+            Provider::S3 | Provider::Gcs => "httpfs",
+            Provider::Azure => "azure",
+        }
+    }
+}
+
+/// A parsed `s3://`, `gs://` or `az://` URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteUri {
+    pub provider: Provider,
+    /// Bucket, or container for Azure.
+    pub bucket: String,
+    /// Object key or glob within the bucket.
+    pub path: String,
+}
+
+impl RemoteUri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidSource(format!("`{uri}`: {reason}"));
+        let (scheme, rest) = uri
+            .split_once("://")
+            .ok_or_else(|| invalid("expected a URI like gs://bucket/path"))?;
+        let provider = match scheme.to_ascii_lowercase().as_str() {
+            "s3" => Provider::S3,
+            "gs" | "gcs" => Provider::Gcs,
+            "az" | "azure" => Provider::Azure,
+            _ => return Err(invalid("unsupported scheme")),
+        };
+        let (bucket, path) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(invalid("missing bucket"));
+        }
+        Ok(Self {
+            provider,
+            bucket: bucket.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// The bucket-level URI a secret is scoped to.
+    fn scope(&self) -> String {
+        let scheme = match self.provider {
+            Provider::S3 => "s3",
+            Provider::Gcs => "gs",
+            Provider::Azure => "az",
+        };
+        format!("{scheme}://{}", self.bucket)
+    }
+}
+
+/// Credentials for one object store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// S3 access keys, or GCS HMAC keys.
+    KeyPair {
+        key_id: String,
+        secret: String,
+        region: Option<String>,
+    },
+    AzureConnectionString(String),
+}
+
+/// Statements loading the extension for `uri` and registering `credentials`
+/// as a DuckDB secret scoped to its bucket.
+pub fn secret_sql(name: &str, uri: &RemoteUri, credentials: &Credentials) -> Result<Vec<String>> {
+    let options = match (uri.provider, credentials) {
+        (
+            Provider::S3 | Provider::Gcs,
+            Credentials::KeyPair {
+                key_id,
+                secret,
+                region,
+            },
+        ) => {
+            let mut options = format!(
+                "KEY_ID {}, SECRET {}",
+                quote_literal(key_id),
+                quote_literal(secret)
+            );
+            if let Some(region) = region {
+                options.push_str(&format!(", REGION {}", quote_literal(region)));
+            }
+            options
+        }
+        (Provider::Azure, Credentials::AzureConnectionString(connection)) => {
+            format!("CONNECTION_STRING {}", quote_literal(connection))
+        }
+        _ => {
+            return Err(Error::InvalidSource(format!(
+                "credentials don't match a {} URI",
+                uri.provider.secret_type()
+            )));
+        }
+    };
+    let extension = uri.provider.extension();
+    Ok(vec![
+        format!("INSTALL {extension}"),
+        format!("LOAD {extension}"),
+        format!(
+            "CREATE OR REPLACE SECRET {} (TYPE {}, {options}, SCOPE {})",
+            quote_ident(name),
+            uri.provider.secret_type(),
+            quote_literal(&uri.scope())
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_supported_schemes() {
+        let uri = RemoteUri::parse("gs://exports/2024/*.parquet").unwrap();
+        assert_eq!(uri.provider, Provider::Gcs);
+        assert_eq!(uri.bucket, "exports");
+        assert_eq!(uri.path, "2024/*.parquet");
+        assert_eq!(
+            RemoteUri::parse("az://landing").unwrap().provider,
+            Provider::Azure
+        );
+        assert!(RemoteUri::parse("ftp://host/file.csv").is_err());
+        assert!(RemoteUri::parse("s3:///key").is_err());
+    }
+
+    #[test]
+    fn registers_scoped_secrets() {
+        let uri = RemoteUri::parse("az://landing/orders.csv").unwrap();
+        let sql = secret_sql(
+            "landing",
+            &uri,
+            &Credentials::AzureConnectionString("AccountName=acme".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            [
+                "INSTALL azure",
+                "LOAD azure",
+                "CREATE OR REPLACE SECRET \"landing\" (TYPE azure, \
+                 CONNECTION_STRING 'AccountName=acme', SCOPE 'az://landing')"
+            ]
+        );
+
+        let gcs = RemoteUri::parse("gs://exports/a.parquet").unwrap();
+        let keys = Credentials::KeyPair {
+            key_id: "GOOG1".into(),
+            secret: "s3cr3t".into(),
+            region: None,
+        };
+        assert_eq!(
+            secret_sql("exports", &gcs, &keys).unwrap()[2],
+            "CREATE OR REPLACE SECRET \"exports\" (TYPE gcs, KEY_ID 'GOOG1', SECRET 's3cr3t', \
+             SCOPE 'gs://exports')"
+        );
+        assert!(secret_sql("landing", &uri, &keys).is_err());
+    }
+}
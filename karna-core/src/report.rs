@@ -0,0 +1,124 @@
+//! Markdown report templates with embedded queries.
+//!
+//! A template is Markdown (inline HTML allowed) where `{{ <sql> }}` marks a
+//! query. Single-value results are inlined; anything else becomes a table.
+
+use pulldown_cmark::{Options, Parser, html};
+
+use crate::error::{Error, Result};
+use crate::result::QueryResult;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// Escapes a value so it reads as literal text inside a Markdown table cell.
+pub(crate) fn escape_cell(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+fn markdown_table(result: &QueryResult) -> String {
+    let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut table = String::from("\n\n");
+    let header = result.columns.iter().map(|c| escape_cell(c)).collect();
+    table.push_str(&row(header));
+    table.push_str(&row(vec!["---".to_string(); result.columns.len()]));
+    for values in &result.rows {
+        let cells = values
+            .iter()
+            .map(|v| v.as_deref().map(escape_cell).unwrap_or_default())
+            .collect();
+        table.push_str(&row(cells));
+    }
+    table.push('\n');
+    table
+}
+
+/// Replaces every placeholder with the output of `run` for its query, as Markdown.
+pub fn expand<F>(template: &str, mut run: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<QueryResult>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        let end = after
+            .find(CLOSE)
+            .ok_or_else(|| Error::InvalidTemplate("unterminated `{{` placeholder".into()))?;
+        let sql = after[..end].trim();
+        if sql.is_empty() {
+            return Err(Error::InvalidTemplate("empty query placeholder".into()));
+        }
+        let result = run(sql)?;
+        match result.scalar() {
+            Some(value) => out.push_str(&value.map(escape_cell).unwrap_or_default()),
+            None => out.push_str(&markdown_table(&result)),
+        }
+        rest = &after[end + CLOSE.len()..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Expands the template's queries and renders the report as an HTML fragment.
+pub fn render_html<F>(template: &str, run: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<QueryResult>,
+{
+    let markdown = expand(template, run)?;
+    let mut html = String::new();
+    html::push_html(
+        &mut html,
+        Parser::new_ext(&markdown, Options::ENABLE_TABLES),
+    );
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(sql: &str) -> Result<QueryResult> {
+        Ok(match sql {
+            "SELECT count(*) FROM orders" => QueryResult {
+                columns: vec!["count".into()],
+                rows: vec![vec![Some("42".into())]],
+            },
+            _ => QueryResult {
+                columns: vec!["region".into(), "total".into()],
+                rows: vec![
+                    vec![Some("<EU>".into()), Some("10".into())],
+                    vec![Some("a|b".into()), None],
+                ],
+            },
+        })
+    }
+
+    #[test]
+    fn inlines_scalars_and_tabulates_results() {
+        let html = render_html(
+            "# Weekly\n\nOrders: {{ SELECT count(*) FROM orders }}\n\n{{SELECT * FROM by_region}}",
+            run,
+        )
+        .unwrap();
+        assert!(html.contains("<h1>Weekly</h1>"));
+        assert!(html.contains("<p>Orders: 42</p>"));
+        assert!(html.contains("<th>region</th>"));
+        assert!(html.contains("<td>&lt;EU&gt;</td>"));
+        assert!(html.contains("<td>a|b</td>"));
+    }
+
+    #[test]
+    fn rejects_unterminated_placeholders() {
+        assert!(matches!(
+            expand("{{ SELECT 1", run),
+            Err(Error::InvalidTemplate(_))
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+//! Tabular query results passed between the engine and features built on top of it.
+
+/// Rows of a query result rendered as text; `None` is SQL NULL.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+impl QueryResult {
+    /// The single value of a one-row, one-column result.
+    pub fn scalar(&self) -> Option<Option<&str>> {
+        match (self.columns.len(), self.rows.as_slice()) {
+            (1, [row]) => row.first().map(Option::as_deref),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,521 @@
+//! Rewrites applied to parsed SQL before it is handed to DuckDB.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    CopySource, Expr, Ident, ObjectName, Query, Statement, TableAlias, TableFactor, Value, Visit,
+    VisitMut, Visitor, VisitorMut,
+};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+
+use crate::error::{Error, Result};
+
+/// Access rules enforced on a single dataset table for the querying role.
+#[derive(Debug, Clone, Default)]
+pub struct TablePolicy {
+    /// Predicate every visible row must satisfy, e.g. `region = 'EU'`.
+    pub row_filter: Option<String>,
+    /// Columns whose values are replaced before they leave the table, keyed by column name.
+    pub masks: BTreeMap<String, Mask>,
+}
+
+/// How a masked column is presented to users without access to the raw values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mask {
+    /// Replace values with their MD5 hash, so equal values still group and join together.
+    Hash,
+    /// Replace every non-null value with a fixed placeholder.
+    Redact,
+}
+
+impl Mask {
+    fn expr(self, column: &Ident) -> String {
+        match self {
+            Mask::Hash => format!("md5(CAST({column} AS VARCHAR))"),
+            Mask::Redact => format!("CASE WHEN {column} IS NULL THEN NULL ELSE '***' END"),
+        }
+    }
+}
+
+/// Validated form of a [`TablePolicy`], ready to be spliced into queries.
+struct CompiledPolicy {
+    row_filter: Option<Expr>,
+    masks: Vec<(Ident, Mask)>,
+}
+
+impl CompiledPolicy {
+    fn compile(table: &str, policy: &TablePolicy) -> Result<Self> {
+        let row_filter = policy
+            .row_filter
+            .as_deref()
+            .map(|predicate| parse_predicate(table, predicate))
+            .transpose()?;
+        let masks = policy
+            .masks
+            .iter()
+            .map(|(column, mask)| (Ident::with_quote('"', column), *mask))
+            .collect();
+        Ok(Self { row_filter, masks })
+    }
+
+    fn subquery(&self, name: &ObjectName) -> Result<Box<Query>> {
+        let mut sql = String::from("SELECT *");
+        if !self.masks.is_empty() {
+            let replacements = self
+                .masks
+                .iter()
+                .map(|(column, mask)| format!("{} AS {column}", mask.expr(column)))
+                .collect::<Vec<_>>();
+            sql.push_str(&format!(" REPLACE ({})", replacements.join(", ")));
+        }
+        sql.push_str(&format!(" FROM {name}"));
+        if let Some(filter) = &self.row_filter {
+            sql.push_str(&format!(" WHERE {filter}"));
+        }
+        Ok(Parser::new(&DuckDbDialect {})
+            .try_with_sql(&sql)?
+            .parse_query()?)
+    }
+}
+
+/// Parses a policy predicate, rejecting anything that is not a single expression.
+fn parse_predicate(table: &str, predicate: &str) -> Result<Expr> {
+    let invalid = |reason: String| Error::InvalidPolicy {
+        table: table.to_string(),
+        reason,
+    };
+    let mut parser = Parser::new(&DuckDbDialect {})
+        .try_with_sql(predicate)
+        .map_err(|e| invalid(e.to_string()))?;
+    let expr = parser.parse_expr().map_err(|e| invalid(e.to_string()))?;
+    if parser.peek_token().token != Token::EOF {
+        return Err(invalid(
+            "row filter must be a single expression".to_string(),
+        ));
+    }
+    Ok(expr)
+}
+
+/// Table functions allowed alongside policies: they generate rows and can't
+/// read dataset storage, unlike `read_parquet`, `read_csv` or `query_table`.
+const ALLOWED_TABLE_FUNCTIONS: &[&str] = &["generate_series", "range", "unnest"];
+
+/// Rejects table functions outside [`ALLOWED_TABLE_FUNCTIONS`] and file
+/// paths used as tables, either of which could read a policed dataset's
+/// files without going through its policy.
+fn guard_table_source(factor: &TableFactor) -> Result<()> {
+    let function = match factor {
+        TableFactor::Table {
+            name,
+            args: Some(_),
+            ..
+        }
+        | TableFactor::Function { name, .. } => name.to_string(),
+        TableFactor::TableFunction { expr, .. } => match expr {
+            Expr::Function(function) => function.name.to_string(),
+            other => other.to_string(),
+        },
+        TableFactor::Table { name, .. }
+            if name
+                .0
+                .iter()
+                .any(|part| part.quote_style == Some('\'') || part.value.contains(['/', '\\'])) =>
+        {
+            name.to_string()
+        }
+        _ => return Ok(()),
+    };
+    let allowed = function
+        .rsplit('.')
+        .next()
+        .is_some_and(|f| ALLOWED_TABLE_FUNCTIONS.contains(&f.to_lowercase().as_str()));
+    if allowed {
+        Ok(())
+    } else {
+        Err(Error::PolicyFunction { function })
+    }
+}
+
+struct PolicyRewriter<'a> {
+    policies: &'a HashMap<String, CompiledPolicy>,
+}
+
+impl VisitorMut for PolicyRewriter<'_> {
+    type Break = Error;
+
+    // Rewriting after the factor's children have been visited keeps the
+    // visitor from descending into the subquery it just inserted.
+    fn post_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<Self::Break> {
+        if !self.policies.is_empty()
+            && let Err(e) = guard_table_source(factor)
+        {
+            return ControlFlow::Break(e);
+        }
+        let TableFactor::Table {
+            name,
+            alias,
+            args: None,
+            ..
+        } = factor
+        else {
+            return ControlFlow::Continue(());
+        };
+        let Some(table) = name.0.last() else {
+            return ControlFlow::Continue(());
+        };
+        let Some(policy) = self.policies.get(&table.value.to_lowercase()) else {
+            return ControlFlow::Continue(());
+        };
+
+        let subquery = match policy.subquery(name) {
+            Ok(subquery) => subquery,
+            Err(e) => return ControlFlow::Break(e),
+        };
+        // Keep the original alias, or alias the subquery as the table itself
+        // so qualified column references like `sales.amount` keep resolving.
+        let alias = alias.take().unwrap_or_else(|| TableAlias {
+            name: table.clone(),
+            columns: vec![],
+        });
+        *factor = TableFactor::Derived {
+            lateral: false,
+            subquery,
+            alias: Some(alias),
+        };
+        ControlFlow::Continue(())
+    }
+}
+
+/// Tables a statement names outside of any query, e.g. the target of an
+/// `INSERT` or the table of a `DESCRIBE`.
+#[derive(Default)]
+struct NonQueryRelations {
+    depth: usize,
+    names: Vec<ObjectName>,
+}
+
+impl Visitor for NonQueryRelations {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.depth += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.depth -= 1;
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
+        if self.depth == 0 {
+            self.names.push(relation.clone());
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Turns `COPY <policed table> TO` into a `COPY (SELECT ...) TO` the policy
+/// rewrite then applies to, and rejects any other statement naming a policed
+/// table outside a query, where no policy could be enforced.
+fn guard_statement(
+    statement: &mut Statement,
+    policies: &HashMap<String, CompiledPolicy>,
+) -> Result<()> {
+    let policed = |name: &ObjectName| {
+        name.0
+            .last()
+            .is_some_and(|table| policies.contains_key(&table.value.to_lowercase()))
+    };
+    let denied = |name: &ObjectName| Error::PolicyViolation {
+        table: name.to_string(),
+    };
+    if let Statement::Copy {
+        source: source @ CopySource::Table { .. },
+        to,
+        ..
+    } = statement
+        && let CopySource::Table {
+            table_name,
+            columns,
+        } = &*source
+        && policed(table_name)
+    {
+        if !*to {
+            return Err(denied(table_name));
+        }
+        let projection = if columns.is_empty() {
+            "*".to_string()
+        } else {
+            columns
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let query = Parser::new(&DuckDbDialect {})
+            .try_with_sql(&format!("SELECT {projection} FROM {table_name}"))?
+            .parse_query()?;
+        *source = CopySource::Query(query);
+    }
+
+    let mut relations = NonQueryRelations::default();
+    let _ = Visit::visit(&*statement, &mut relations);
+    if let Statement::Drop { names, .. } = statement {
+        relations.names.extend(names.iter().cloned());
+    }
+    match relations.names.iter().find(|name| policed(name)) {
+        Some(name) => Err(denied(name)),
+        None => Ok(()),
+    }
+}
+
+/// Rewrites every reference to a policed table into a subquery enforcing its policy.
+///
+/// `policies` is keyed by table name; matching is case-insensitive on the
+/// unqualified name. `COPY ... TO` a file reads through the same subquery;
+/// other statements naming a policed table outside a query, such as
+/// `DESCRIBE` or `INSERT`, are rejected, as are table functions and file
+/// paths that could read dataset storage directly.
+pub fn apply_policies(sql: &str, policies: &HashMap<String, TablePolicy>) -> Result<String> {
+    let compiled = policies
+        .iter()
+        .map(|(table, policy)| {
+            Ok((
+                table.to_lowercase(),
+                CompiledPolicy::compile(table, policy)?,
+            ))
+        })
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    let mut statements = Parser::parse_sql(&DuckDbDialect {}, sql)?;
+    for statement in &mut statements {
+        guard_statement(statement, &compiled)?;
+    }
+    if let ControlFlow::Break(e) = VisitMut::visit(
+        &mut statements,
+        &mut PolicyRewriter {
+            policies: &compiled,
+        },
+    ) {
+        return Err(e);
+    }
+
+    Ok(statements
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+/// Caps the rows returned by each top-level query at `max`, so an interactive
+/// query can't pull a whole table into the browser.
+///
+/// Queries without a `LIMIT` get one, and literal limits above `max` are
+/// lowered; other statements, and queries using `FETCH` or a computed limit,
+/// are left alone. Exports and users who opt out skip this rewrite.
+pub fn limit_rows(sql: &str, max: u64) -> Result<String> {
+    let mut statements = Parser::parse_sql(&DuckDbDialect {}, sql)?;
+    for statement in &mut statements {
+        let Statement::Query(query) = statement else {
+            continue;
+        };
+        if query.fetch.is_some() {
+            continue;
+        }
+        let within = match &query.limit {
+            None => false,
+            Some(Expr::Value(Value::Number(n, _))) => n.parse::<u64>().is_ok_and(|n| n <= max),
+            Some(_) => true,
+        };
+        if !within {
+            query.limit = Some(Expr::Value(Value::Number(max.to_string(), false)));
+        }
+    }
+    Ok(statements
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(table: &str, predicate: &str) -> HashMap<String, TablePolicy> {
+        HashMap::from([(
+            table.to_string(),
+            TablePolicy {
+                row_filter: Some(predicate.to_string()),
+                ..Default::default()
+            },
+        )])
+    }
+
+    #[test]
+    fn injects_row_filter_and_keeps_alias() {
+        let sql = apply_policies(
+            "SELECT s.amount FROM sales s JOIN stores ON s.store_id = stores.id",
+            &filter("Sales", "region = 'EU'"),
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT s.amount FROM (SELECT * FROM sales WHERE region = 'EU') AS s \
+             JOIN stores ON s.store_id = stores.id"
+        );
+    }
+
+    #[test]
+    fn aliases_subquery_as_table_name() {
+        let sql =
+            apply_policies("SELECT sales.id FROM sales", &filter("sales", "id > 10")).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT sales.id FROM (SELECT * FROM sales WHERE id > 10) AS sales"
+        );
+    }
+
+    #[test]
+    fn masks_columns_alongside_row_filter() {
+        let policies = HashMap::from([(
+            "users".to_string(),
+            TablePolicy {
+                row_filter: Some("active".to_string()),
+                masks: BTreeMap::from([
+                    ("email".to_string(), Mask::Hash),
+                    ("ssn".to_string(), Mask::Redact),
+                ]),
+            },
+        )]);
+        let sql = apply_policies("SELECT email FROM users", &policies).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT email FROM (SELECT * REPLACE (md5(CAST(\"email\" AS VARCHAR)) AS \"email\", \
+             CASE WHEN \"ssn\" IS NULL THEN NULL ELSE '***' END AS \"ssn\") \
+             FROM users WHERE active) AS users"
+        );
+    }
+
+    #[test]
+    fn rejects_predicates_that_are_not_a_single_expression() {
+        let err = apply_policies(
+            "SELECT * FROM sales",
+            &filter("sales", "1 = 1; DROP TABLE x"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::InvalidPolicy { .. }));
+    }
+
+    #[test]
+    fn copies_policed_tables_through_the_policy() {
+        let sql = apply_policies(
+            "COPY sales (id, amount) TO '/tmp/sales.csv' (FORMAT csv)",
+            &filter("sales", "region = 'EU'"),
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "COPY (SELECT id, amount FROM (SELECT * FROM sales WHERE region = 'EU') AS sales) \
+             TO '/tmp/sales.csv' (FORMAT csv)"
+        );
+    }
+
+    #[test]
+    fn masks_columns_in_copy_exports() {
+        let policies = HashMap::from([(
+            "users".to_string(),
+            TablePolicy {
+                row_filter: None,
+                masks: BTreeMap::from([
+                    ("email".to_string(), Mask::Hash),
+                    ("ssn".to_string(), Mask::Redact),
+                ]),
+            },
+        )]);
+        let sql = apply_policies("COPY users TO '/tmp/users.parquet'", &policies).unwrap();
+        assert_eq!(
+            sql,
+            "COPY (SELECT * FROM (SELECT * REPLACE (md5(CAST(\"email\" AS VARCHAR)) AS \"email\", \
+             CASE WHEN \"ssn\" IS NULL THEN NULL ELSE '***' END AS \"ssn\") FROM users) AS users) \
+             TO '/tmp/users.parquet'"
+        );
+    }
+
+    #[test]
+    fn rejects_other_statements_naming_policed_tables() {
+        for sql in [
+            "DESCRIBE sales",
+            "SHOW COLUMNS FROM sales",
+            "COPY sales FROM '/tmp/sales.csv'",
+            "INSERT INTO sales SELECT * FROM staging",
+            "DROP TABLE sales",
+        ] {
+            assert!(
+                matches!(
+                    apply_policies(sql, &filter("sales", "region = 'EU'")),
+                    Err(Error::PolicyViolation { .. })
+                ),
+                "{sql}"
+            );
+        }
+        assert!(apply_policies("DESCRIBE stores", &filter("sales", "region = 'EU'")).is_ok());
+    }
+
+    #[test]
+    fn rejects_table_functions_reading_storage() {
+        let policies = filter("sales", "region = 'EU'");
+        for sql in [
+            "SELECT * FROM query_table('sales')",
+            "SELECT * FROM read_parquet('/data/datasets/sales.parquet')",
+            "SELECT * FROM stores JOIN read_csv('/data/sales.csv') USING (id)",
+            "SELECT * FROM stores WHERE id IN (SELECT id FROM main.read_json_auto('x.json'))",
+            "SELECT * FROM '/data/datasets/sales.parquet'",
+            "SELECT * FROM \"/data/datasets/sales.parquet\"",
+        ] {
+            assert!(
+                matches!(
+                    apply_policies(sql, &policies),
+                    Err(Error::PolicyFunction { .. })
+                ),
+                "{sql}"
+            );
+        }
+        assert!(apply_policies("SELECT * FROM range(10) AS r(i)", &policies).is_ok());
+        assert!(
+            apply_policies(
+                "SELECT * FROM read_parquet('/data/sales.parquet')",
+                &HashMap::new()
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn caps_top_level_queries_at_the_interactive_limit() {
+        assert_eq!(
+            limit_rows("SELECT * FROM sales", 1000).unwrap(),
+            "SELECT * FROM sales LIMIT 1000"
+        );
+        assert_eq!(
+            limit_rows("SELECT * FROM sales LIMIT 5000 OFFSET 10", 1000).unwrap(),
+            "SELECT * FROM sales LIMIT 1000 OFFSET 10"
+        );
+        assert_eq!(
+            limit_rows(
+                "SELECT * FROM (SELECT * FROM sales) AS s UNION ALL SELECT * FROM t LIMIT 10",
+                1000
+            )
+            .unwrap(),
+            "SELECT * FROM (SELECT * FROM sales) AS s UNION ALL SELECT * FROM t LIMIT 10"
+        );
+        assert_eq!(
+            limit_rows("CREATE TABLE t AS SELECT * FROM sales", 1000).unwrap(),
+            "CREATE TABLE t AS SELECT * FROM sales"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Concurrency slots for queries, with headroom kept for interactive work.
+
+use std::sync::{Condvar, Mutex};
+
+/// Who is waiting on a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// A user in the UI, e.g. clicking "Preview".
+    Interactive,
+    /// Scheduled refreshes and model runs.
+    Scheduled,
+    Export,
+}
+
+/// Limits how many queries run at once. `reserved` of the `slots` are only
+/// handed to interactive queries, so background work can never fill the box.
+#[derive(Debug)]
+pub struct QueryScheduler {
+    slots: usize,
+    reserved: usize,
+    in_use: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl QueryScheduler {
+    pub fn new(slots: usize, reserved: usize) -> Self {
+        let slots = slots.max(1);
+        Self {
+            slots,
+            // Background work always gets at least one slot.
+            reserved: reserved.min(slots - 1),
+            in_use: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    fn limit(&self, priority: Priority) -> usize {
+        match priority {
+            Priority::Interactive => self.slots,
+            Priority::Scheduled | Priority::Export => self.slots - self.reserved,
+        }
+    }
+
+    /// Takes a slot if one is available to `priority` right now.
+    pub fn try_acquire(&self, priority: Priority) -> Option<Slot<'_>> {
+        let mut in_use = self.in_use.lock().unwrap();
+        if *in_use >= self.limit(priority) {
+            return None;
+        }
+        *in_use += 1;
+        Some(Slot { scheduler: self })
+    }
+
+    /// Waits for a slot available to `priority`.
+    pub fn acquire(&self, priority: Priority) -> Slot<'_> {
+        let limit = self.limit(priority);
+        let mut in_use = self
+            .freed
+            .wait_while(self.in_use.lock().unwrap(), |in_use| *in_use >= limit)
+            .unwrap();
+        *in_use += 1;
+        Slot { scheduler: self }
+    }
+
+    pub fn running(&self) -> usize {
+        *self.in_use.lock().unwrap()
+    }
+}
+
+/// A running query's slot, released when dropped.
+#[derive(Debug)]
+pub struct Slot<'a> {
+    scheduler: &'a QueryScheduler,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        *self.scheduler.in_use.lock().unwrap() -= 1;
+        self.scheduler.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_reserved_slots_for_interactive_queries() {
+        let scheduler = QueryScheduler::new(3, 1);
+        let refresh = scheduler.try_acquire(Priority::Scheduled).unwrap();
+        let export = scheduler.try_acquire(Priority::Export).unwrap();
+        assert!(scheduler.try_acquire(Priority::Scheduled).is_none());
+
+        let preview = scheduler.try_acquire(Priority::Interactive).unwrap();
+        assert!(scheduler.try_acquire(Priority::Interactive).is_none());
+        assert_eq!(scheduler.running(), 3);
+
+        drop(preview);
+        assert!(scheduler.try_acquire(Priority::Scheduled).is_none());
+        drop(refresh);
+        let _next = scheduler.acquire(Priority::Scheduled);
+        drop(export);
+        assert_eq!(scheduler.running(), 1);
+    }
+
+    #[test]
+    fn never_reserves_every_slot() {
+        let scheduler = QueryScheduler::new(1, 5);
+        assert!(scheduler.try_acquire(Priority::Export).is_some());
+    }
+}
@@ -0,0 +1,66 @@
+//! Column descriptions shared by features that reason about dataset schemas.
+
+/// A column as reported by DuckDB's `DESCRIBE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+}
+
+impl ColumnInfo {
+    pub fn new(name: impl Into<String>, data_type: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            data_type: data_type.into(),
+        }
+    }
+
+    pub fn family(&self) -> TypeFamily {
+        TypeFamily::of(&self.data_type)
+    }
+}
+
+/// Coarse grouping of DuckDB types by how their values can be compared and charted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeFamily {
+    Numeric,
+    Text,
+    Temporal,
+    Boolean,
+    Other,
+}
+
+impl TypeFamily {
+    pub fn of(data_type: &str) -> Self {
+        let upper = data_type.trim().to_ascii_uppercase();
+        // Parameterized types such as DECIMAL(18,3) or VARCHAR(20).
+        let base = upper.split('(').next().unwrap_or_default().trim();
+        match base {
+            "TINYINT" | "SMALLINT" | "INTEGER" | "INT" | "BIGINT" | "HUGEINT" | "UTINYINT"
+            | "USMALLINT" | "UINTEGER" | "UBIGINT" | "UHUGEINT" | "FLOAT" | "REAL" | "DOUBLE"
+            | "DECIMAL" | "NUMERIC" => TypeFamily::Numeric,
+            "VARCHAR" | "TEXT" | "STRING" | "CHAR" | "BPCHAR" => TypeFamily::Text,
+            "BOOLEAN" | "BOOL" => TypeFamily::Boolean,
+            _ if base == "DATE" || base.starts_with("TIME") => TypeFamily::Temporal,
+            _ => TypeFamily::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_duckdb_types() {
+        assert_eq!(TypeFamily::of("DECIMAL(18,3)"), TypeFamily::Numeric);
+        assert_eq!(TypeFamily::of("ubigint"), TypeFamily::Numeric);
+        assert_eq!(TypeFamily::of("VARCHAR"), TypeFamily::Text);
+        assert_eq!(
+            TypeFamily::of("TIMESTAMP WITH TIME ZONE"),
+            TypeFamily::Temporal
+        );
+        assert_eq!(TypeFamily::of("DATE"), TypeFamily::Temporal);
+        assert_eq!(TypeFamily::of("UUID"), TypeFamily::Other);
+    }
+}
@@ -0,0 +1,100 @@
+//! Scripts of several statements, run one at a time so each statement's
+//! result set can be shown in its own pane.
+
+use sqlparser::parser::ParserError;
+use sqlparser::tokenizer::Token;
+
+use crate::error::Result;
+use crate::result::QueryResult;
+use crate::sql::token_spans;
+
+/// Output of one statement of a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementResult {
+    /// Position of the statement in the script, from 0.
+    pub index: usize,
+    pub statement: String,
+    pub result: QueryResult,
+}
+
+/// Splits `sql` on top-level `;`, keeping each statement's text as written.
+///
+/// Semicolons inside string literals, quoted identifiers and comments don't
+/// split; empty statements are dropped.
+pub fn split_statements(sql: &str) -> Result<Vec<String>> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    for (token, span) in token_spans(sql).map_err(ParserError::from)? {
+        if token == Token::SemiColon {
+            statements.push(&sql[start..span.start]);
+            start = span.end;
+        }
+    }
+    statements.push(&sql[start..]);
+    Ok(statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Runs each statement of `sql` in order with `run`, stopping at the first error.
+pub fn run_script<F>(sql: &str, mut run: F) -> Result<Vec<StatementResult>>
+where
+    F: FnMut(&str) -> Result<QueryResult>,
+{
+    split_statements(sql)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, statement)| {
+            let result = run(&statement)?;
+            Ok(StatementResult {
+                index,
+                statement,
+                result,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_top_level_semicolons_only() {
+        let script = "CREATE TABLE t AS SELECT 'a;b' AS \"x;y\";\n\
+                      -- comment; still a comment\n\
+                      SELECT * FROM t;;\n  ";
+        assert_eq!(
+            split_statements(script).unwrap(),
+            [
+                "CREATE TABLE t AS SELECT 'a;b' AS \"x;y\"",
+                "-- comment; still a comment\nSELECT * FROM t",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_quotes_as_written() {
+        assert_eq!(
+            split_statements("SELECT 'it''s' AS x; SELECT \"a\"\"b\" FROM t").unwrap(),
+            ["SELECT 'it''s' AS x", "SELECT \"a\"\"b\" FROM t"]
+        );
+    }
+
+    #[test]
+    fn returns_one_result_per_statement() {
+        let results = run_script("SELECT 1; SELECT 2", |sql| {
+            Ok(QueryResult {
+                columns: vec!["sql".into()],
+                rows: vec![vec![Some(sql.to_string())]],
+            })
+        })
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].index, 1);
+        assert_eq!(results[1].result.scalar(), Some(Some("SELECT 2")));
+    }
+}
@@ -0,0 +1,230 @@
+//! DuckDB settings applied to the database and to the connection serving a
+//! piece of work.
+//!
+//! Most DuckDB settings, including `memory_limit` and `threads`, are
+//! database-wide: setting them on one connection changes them for every
+//! concurrent session. Only `TimeZone` is applied per session.
+
+use std::path::PathBuf;
+
+use crate::sql::quote_literal;
+
+/// CSV `sample_size` used when an ingest is retried after running out of memory.
+pub const LOW_MEMORY_SAMPLE_SIZE: u64 = 2048;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionSettings {
+    pub memory_limit_mb: Option<u64>,
+    pub threads: Option<u32>,
+    /// Where DuckDB spills intermediate results once `memory_limit` is reached.
+    pub temp_directory: Option<PathBuf>,
+    pub preserve_insertion_order: Option<bool>,
+    /// IANA zone used by ICU when rendering and truncating `TIMESTAMPTZ` values.
+    pub time_zone: Option<String>,
+}
+
+impl ConnectionSettings {
+    /// `SET` statements for every configured setting, run once with the
+    /// workspace's settings when the database is opened.
+    pub fn statements(&self) -> Vec<String> {
+        let mut statements = Vec::new();
+        if let Some(mb) = self.memory_limit_mb {
+            statements.push(format!("SET memory_limit = '{mb}MB'"));
+        }
+        if let Some(threads) = self.threads {
+            statements.push(format!("SET threads = {threads}"));
+        }
+        if let Some(dir) = &self.temp_directory {
+            statements.push(format!(
+                "SET temp_directory = {}",
+                quote_literal(&dir.to_string_lossy())
+            ));
+        }
+        if let Some(preserve) = self.preserve_insertion_order {
+            statements.push(format!("SET preserve_insertion_order = {preserve}"));
+        }
+        if let Some(zone) = &self.time_zone {
+            statements.push(format!("SET TimeZone = {}", quote_literal(zone)));
+        }
+        statements
+    }
+
+    /// `SET` statements for the settings scoped to one session, run on the
+    /// connection serving a query. Database-wide settings are left out.
+    pub fn session_statements(&self) -> Vec<String> {
+        self.time_zone
+            .iter()
+            .map(|zone| format!("SET SESSION TimeZone = {}", quote_literal(zone)))
+            .collect()
+    }
+
+    /// `RESET` statements undoing [`Self::session_statements`], run once the
+    /// query finishes.
+    pub fn reset_statements(&self) -> Vec<String> {
+        self.time_zone
+            .iter()
+            .map(|_| "RESET SESSION TimeZone".to_string())
+            .collect()
+    }
+
+    /// Workspace resource limits capped at the admin-set `ceiling`; settings
+    /// other than memory and threads are not taken from the request.
+    ///
+    /// These are database-wide, so they are applied with [`Self::statements`]
+    /// when the workspace database is opened, never per query.
+    pub fn limited_to(&self, ceiling: &Self) -> Self {
+        fn cap<T: Ord + Copy>(requested: Option<T>, ceiling: Option<T>) -> Option<T> {
+            match (requested, ceiling) {
+                (Some(r), Some(c)) => Some(r.min(c)),
+                (r, c) => r.or(c),
+            }
+        }
+        Self {
+            memory_limit_mb: cap(self.memory_limit_mb, ceiling.memory_limit_mb),
+            threads: cap(self.threads, ceiling.threads),
+            ..Self::default()
+        }
+    }
+
+    /// The same settings, relaxed so a large ingest can stream instead of buffering.
+    pub fn low_memory(&self) -> Self {
+        Self {
+            preserve_insertion_order: Some(false),
+            ..self.clone()
+        }
+    }
+}
+
+/// Time zone a query runs in: the user's preference, else the workspace's, else UTC.
+/// Blank values count as unset.
+pub fn resolve_time_zone<'a>(user: Option<&'a str>, workspace: Option<&'a str>) -> &'a str {
+    let set = |zone: Option<&'a str>| zone.map(str::trim).filter(|zone| !zone.is_empty());
+    set(user).or(set(workspace)).unwrap_or("UTC")
+}
+
+/// Whether a DuckDB error message reports memory exhaustion.
+pub fn is_out_of_memory(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("out of memory") || message.contains("failed to allocate")
+}
+
+/// Which settings an ingest attempt runs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempt {
+    Normal,
+    /// Retry after an out-of-memory failure: use [`ConnectionSettings::low_memory`]
+    /// and [`LOW_MEMORY_SAMPLE_SIZE`].
+    LowMemory,
+}
+
+/// Runs `work`, retrying once in [`Attempt::LowMemory`] mode if it fails with an
+/// out-of-memory error. Other errors are returned as-is.
+pub fn retry_on_oom<T, E: ToString>(mut work: impl FnMut(Attempt) -> Result<T, E>) -> Result<T, E> {
+    match work(Attempt::Normal) {
+        Err(e) if is_out_of_memory(&e.to_string()) => work(Attempt::LowMemory),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_set_statements() {
+        let settings = ConnectionSettings {
+            memory_limit_mb: Some(4096),
+            threads: None,
+            temp_directory: Some(PathBuf::from("/var/karna/spill")),
+            preserve_insertion_order: None,
+            time_zone: Some("Europe/Madrid".into()),
+        };
+        assert_eq!(
+            settings.low_memory().statements(),
+            [
+                "SET memory_limit = '4096MB'",
+                "SET temp_directory = '/var/karna/spill'",
+                "SET preserve_insertion_order = false",
+                "SET TimeZone = 'Europe/Madrid'",
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes_only_the_time_zone_to_sessions() {
+        let settings = ConnectionSettings {
+            memory_limit_mb: Some(4096),
+            threads: Some(8),
+            time_zone: Some("Europe/Madrid".into()),
+            ..ConnectionSettings::default()
+        };
+        assert_eq!(
+            settings.session_statements(),
+            ["SET SESSION TimeZone = 'Europe/Madrid'"]
+        );
+        assert_eq!(settings.reset_statements(), ["RESET SESSION TimeZone"]);
+    }
+
+    #[test]
+    fn caps_workspace_limits_at_admin_ceilings() {
+        let ceiling = ConnectionSettings {
+            memory_limit_mb: Some(8192),
+            threads: Some(4),
+            ..ConnectionSettings::default()
+        };
+        let requested = ConnectionSettings {
+            memory_limit_mb: Some(16384),
+            threads: Some(2),
+            time_zone: Some("Asia/Tokyo".into()),
+            ..ConnectionSettings::default()
+        };
+        let applied = requested.limited_to(&ceiling);
+        assert_eq!(
+            applied.statements(),
+            ["SET memory_limit = '8192MB'", "SET threads = 2"]
+        );
+        assert!(applied.session_statements().is_empty());
+        assert!(applied.reset_statements().is_empty());
+        assert_eq!(ConnectionSettings::default().limited_to(&ceiling), ceiling);
+    }
+
+    #[test]
+    fn user_time_zone_overrides_workspace() {
+        assert_eq!(
+            resolve_time_zone(Some("Asia/Tokyo"), Some("Europe/Paris")),
+            "Asia/Tokyo"
+        );
+        assert_eq!(
+            resolve_time_zone(None, Some("Europe/Paris")),
+            "Europe/Paris"
+        );
+        assert_eq!(
+            resolve_time_zone(Some("  "), Some(" Europe/Paris ")),
+            "Europe/Paris"
+        );
+        assert_eq!(resolve_time_zone(Some(""), Some("")), "UTC");
+        assert_eq!(resolve_time_zone(None, None), "UTC");
+    }
+
+    #[test]
+    fn retries_only_out_of_memory_failures() {
+        let mut attempts = Vec::new();
+        let result = retry_on_oom(|attempt| {
+            attempts.push(attempt);
+            match attempt {
+                Attempt::Normal => Err("Out of Memory Error: failed to allocate block".to_string()),
+                Attempt::LowMemory => Ok(42),
+            }
+        });
+        assert_eq!(result, Ok(42));
+        assert_eq!(attempts, [Attempt::Normal, Attempt::LowMemory]);
+
+        let mut calls = 0;
+        let result: Result<(), String> = retry_on_oom(|_| {
+            calls += 1;
+            Err("Conversion Error".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,71 @@
+//! Helpers for splicing names and values into generated DuckDB SQL.
+
+use std::ops::Range;
+
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::tokenizer::{Location, Token, Tokenizer, TokenizerError};
+
+/// Quotes an identifier, doubling any embedded double quotes.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes a string literal, doubling any embedded single quotes.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Tokens of `sql` with the byte range each one covers, for edits that must
+/// leave the rest of the text exactly as written.
+///
+/// Rebuilding SQL from [`Token`]'s `Display` is lossy: it doesn't re-escape
+/// quotes inside literals and quoted identifiers.
+pub(crate) fn token_spans(sql: &str) -> Result<Vec<(Token, Range<usize>)>, TokenizerError> {
+    let tokens = Tokenizer::new(&DuckDbDialect {}, sql).tokenize_with_location()?;
+    // Locations are 1-based (line, column) pairs counted in chars; tokens come
+    // in order, so one forward pass over the text resolves them all.
+    let mut chars = sql.char_indices().peekable();
+    let (mut line, mut column) = (1, 1);
+    let mut offset = |location: Location| {
+        while (line, column) < (location.line, location.column) {
+            match chars.next() {
+                Some((_, '\n')) => (line, column) = (line + 1, 1),
+                Some(_) => column += 1,
+                None => break,
+            }
+        }
+        chars.peek().map_or(sql.len(), |&(i, _)| i)
+    };
+    Ok(tokens
+        .into_iter()
+        .map(|t| {
+            let start = offset(t.span.start);
+            let end = offset(t.span.end);
+            (t.token, start..end)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_embedded_quotes() {
+        assert_eq!(quote_ident(r#"my "col""#), r#""my ""col""""#);
+        assert_eq!(quote_literal("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn spans_cover_the_original_text() {
+        let sql = "SELECT 'it''s',\n  \"a\"\"b\" -- é\nFROM t";
+        let spans = token_spans(sql).unwrap();
+        let texts = spans
+            .iter()
+            .filter(|(t, _)| !matches!(t, Token::Whitespace(_)))
+            .map(|(_, r)| &sql[r.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["SELECT", "'it''s'", ",", "\"a\"\"b\"", "FROM", "t"]);
+        assert_eq!(spans.last().unwrap().1.end, sql.len());
+    }
+}
@@ -0,0 +1,527 @@
+//! How dataset tables are stored and (re)built in DuckDB.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::sql::{quote_ident, quote_literal};
+
+/// Whether a dataset owns a copy of its data or reads its source on every query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Materialization {
+    /// Data copied into a DuckDB table with `CREATE TABLE ... AS`.
+    Table,
+    /// A view over the source; nothing is copied and there is nothing to refresh.
+    View,
+}
+
+/// External data a virtual dataset is registered over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViewSource {
+    /// Local path, glob or `s3://` URL of parquet files.
+    Parquet { location: String },
+    /// A table in an attached Postgres database.
+    Postgres {
+        connection: String,
+        schema: String,
+        table: String,
+    },
+}
+
+impl ViewSource {
+    /// Catalog alias the Postgres database is attached under for `dataset`.
+    fn attach_alias(dataset: &str) -> String {
+        quote_ident(&format!("pg_{dataset}"))
+    }
+
+    /// Statements that register `dataset` as a view over this source.
+    pub fn create_view_sql(&self, dataset: &str) -> Vec<String> {
+        let view = quote_ident(dataset);
+        match self {
+            ViewSource::Parquet { location } => vec![format!(
+                "CREATE OR REPLACE VIEW {view} AS SELECT * FROM read_parquet({})",
+                quote_literal(location)
+            )],
+            ViewSource::Postgres {
+                connection,
+                schema,
+                table,
+            } => {
+                let alias = Self::attach_alias(dataset);
+                vec![
+                    "INSTALL postgres".to_string(),
+                    "LOAD postgres".to_string(),
+                    format!(
+                        "ATTACH IF NOT EXISTS {} AS {alias} (TYPE postgres, READ_ONLY)",
+                        quote_literal(connection)
+                    ),
+                    format!(
+                        "CREATE OR REPLACE VIEW {view} AS SELECT * FROM {alias}.{}.{}",
+                        quote_ident(schema),
+                        quote_ident(table)
+                    ),
+                ]
+            }
+        }
+    }
+}
+
+/// Statement rebuilding a dataset from `source_select`, or `None` for views,
+/// which always read the latest source data.
+pub fn refresh_sql(
+    dataset: &str,
+    materialization: Materialization,
+    source_select: &str,
+) -> Option<String> {
+    match materialization {
+        Materialization::Table => Some(format!(
+            "CREATE OR REPLACE TABLE {} AS {source_select}",
+            quote_ident(dataset)
+        )),
+        Materialization::View => None,
+    }
+}
+
+/// Parquet compression codecs DuckDB can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Snappy,
+    Zstd,
+}
+
+impl Compression {
+    fn name(self) -> &'static str {
+        match self {
+            Compression::Uncompressed => "uncompressed",
+            Compression::Snappy => "snappy",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// Layout settings applied whenever karna writes dataset parquet files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParquetOptions {
+    pub compression: Compression,
+    pub row_group_size: u64,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::Zstd,
+            row_group_size: 122_880,
+        }
+    }
+}
+
+impl ParquetOptions {
+    /// Options for a `COPY ... TO` statement, without the surrounding parentheses.
+    pub fn copy_options(&self) -> String {
+        format!(
+            "FORMAT parquet, COMPRESSION {}, ROW_GROUP_SIZE {}",
+            self.compression.name(),
+            self.row_group_size
+        )
+    }
+
+    /// `COPY` rewriting existing parquet data at `source` into `target` with these options.
+    ///
+    /// Used by the optimize action; the caller swaps `target` in once it succeeds.
+    pub fn rewrite_sql(&self, source: &str, target: &Path) -> String {
+        format!(
+            "COPY (SELECT * FROM read_parquet({})) TO {} ({})",
+            quote_literal(source),
+            quote_literal(&target.to_string_lossy()),
+            self.copy_options()
+        )
+    }
+}
+
+/// On-disk size of a dataset before and after an optimize rewrite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeReport {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+}
+
+impl SizeReport {
+    /// Bytes saved; negative when the rewrite grew the data.
+    pub fn saved_bytes(&self) -> i64 {
+        self.before_bytes as i64 - self.after_bytes as i64
+    }
+
+    pub fn saved_fraction(&self) -> f64 {
+        if self.before_bytes == 0 {
+            return 0.0;
+        }
+        self.saved_bytes() as f64 / self.before_bytes as f64
+    }
+}
+
+/// Directory name DuckDB gives the partition of `NULL` values.
+pub const HIVE_NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Percent-encodes everything but unreserved URL characters, as DuckDB does
+/// for hive partition keys and values.
+fn hive_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            escaped.push(char::from(byte));
+        } else {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escaped
+}
+
+/// A dataset stored as hive-partitioned parquet under its own directory and
+/// exposed through a view, so DuckDB can prune partitions from filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionedLayout {
+    pub directory: PathBuf,
+    pub partition_by: Vec<String>,
+    pub parquet: ParquetOptions,
+}
+
+impl PartitionedLayout {
+    fn glob(&self) -> String {
+        self.directory
+            .join("**")
+            .join("*.parquet")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Directory holding one partition, given its values in `partition_by`
+    /// order, named the way DuckDB writes it: keys and values URL-encoded and
+    /// `NULL` as [`HIVE_NULL_PARTITION`].
+    ///
+    /// Callers remove these before [`Self::write_sql`] rewrites the partitions,
+    /// so values that could name anything but a child directory (`.`, `..`,
+    /// path separators, NUL) are rejected rather than escaped.
+    pub fn partition_dir(&self, values: &[Option<String>]) -> Result<PathBuf> {
+        let mut dir = self.directory.clone();
+        for (column, value) in self.partition_by.iter().zip(values) {
+            let value = match value {
+                Some(value) => {
+                    if matches!(value.as_str(), "." | "..") || value.contains(['/', '\\', '\0']) {
+                        return Err(Error::InvalidPartition(format!("`{value}` for `{column}`")));
+                    }
+                    hive_escape(value)
+                }
+                None => HIVE_NULL_PARTITION.to_string(),
+            };
+            dir.push(format!("{}={value}", hive_escape(column)));
+        }
+        Ok(dir)
+    }
+
+    /// `COPY` writing the rows of `select` into the partition tree.
+    ///
+    /// With `only` empty the whole tree is replaced. Otherwise just the rows
+    /// belonging to the listed partitions are written, into files with unique
+    /// names next to the partitions left in place; the listed partitions'
+    /// directories must have been removed first.
+    pub fn write_sql(&self, select: &str, only: &[Vec<Option<String>>]) -> String {
+        let columns = self
+            .partition_by
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>();
+        let select = if only.is_empty() {
+            select.to_string()
+        } else {
+            let partitions = only
+                .iter()
+                .map(|values| {
+                    let conditions = columns
+                        .iter()
+                        .zip(values)
+                        .map(|(c, v)| match v {
+                            Some(v) => format!("CAST({c} AS VARCHAR) = {}", quote_literal(v)),
+                            None => format!("{c} IS NULL"),
+                        })
+                        .collect::<Vec<_>>();
+                    format!("({})", conditions.join(" AND "))
+                })
+                .collect::<Vec<_>>();
+            format!("SELECT * FROM ({select}) WHERE {}", partitions.join(" OR "))
+        };
+        let mode = if only.is_empty() {
+            "OVERWRITE"
+        } else {
+            "OVERWRITE_OR_IGNORE, FILENAME_PATTERN 'data_{uuid}'"
+        };
+        format!(
+            "COPY ({select}) TO {} ({}, PARTITION_BY ({}), {mode})",
+            quote_literal(&self.directory.to_string_lossy()),
+            self.parquet.copy_options(),
+            columns.join(", "),
+        )
+    }
+
+    /// View exposing the partition tree as the dataset table.
+    pub fn view_sql(&self, dataset: &str) -> String {
+        format!(
+            "CREATE OR REPLACE VIEW {} AS SELECT * FROM read_parquet({}, hive_partitioning = true)",
+            quote_ident(dataset),
+            quote_literal(&self.glob())
+        )
+    }
+}
+
+/// A dataset kept in its own DuckDB file, attached under `catalog`, that can
+/// be moved to a parquet file while it goes unused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive {
+    pub dataset: String,
+    pub catalog: String,
+    pub database: PathBuf,
+    pub parquet: PathBuf,
+}
+
+impl Archive {
+    fn table(&self) -> String {
+        format!(
+            "{}.{}",
+            quote_ident(&self.catalog),
+            quote_ident(&self.dataset)
+        )
+    }
+
+    /// Statements copying the table to parquet, detaching its database and
+    /// registering a view over the parquet under the dataset's name.
+    ///
+    /// The caller deletes the `.db` file once they succeed.
+    pub fn archive_sql(&self, options: &ParquetOptions) -> Vec<String> {
+        vec![
+            format!(
+                "COPY (SELECT * FROM {}) TO {} ({})",
+                self.table(),
+                quote_literal(&self.parquet.to_string_lossy()),
+                options.copy_options()
+            ),
+            format!("DETACH {}", quote_ident(&self.catalog)),
+            ViewSource::Parquet {
+                location: self.parquet.to_string_lossy().into_owned(),
+            }
+            .create_view_sql(&self.dataset)
+            .remove(0),
+        ]
+    }
+
+    /// Statements rematerializing the table from parquet into a fresh database.
+    ///
+    /// The caller deletes the parquet file once they succeed.
+    pub fn unarchive_sql(&self) -> Vec<String> {
+        vec![
+            format!(
+                "ATTACH {} AS {}",
+                quote_literal(&self.database.to_string_lossy()),
+                quote_ident(&self.catalog)
+            ),
+            format!(
+                "CREATE TABLE {} AS SELECT * FROM read_parquet({})",
+                self.table(),
+                quote_literal(&self.parquet.to_string_lossy())
+            ),
+            format!("DROP VIEW IF EXISTS {}", quote_ident(&self.dataset)),
+        ]
+    }
+}
+
+/// Total size of a dataset's storage: a single `.db`/parquet file or a partition tree.
+///
+/// Symlinks aren't followed, so a link back up the tree can't loop forever.
+pub fn storage_size(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_symlink() {
+        return Ok(0);
+    }
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += storage_size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Query for a dataset's row count.
+///
+/// The estimate comes from DuckDB's catalog and is free to read, which matters
+/// after large appends; the exact count scans the table.
+pub fn row_count_sql(dataset: &str, exact: bool) -> String {
+    if exact {
+        format!("SELECT count(*) FROM {}", quote_ident(dataset))
+    } else {
+        format!(
+            "SELECT estimated_size FROM duckdb_tables() WHERE table_name = {}",
+            quote_literal(dataset)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_parquet_and_postgres_views() {
+        let parquet = ViewSource::Parquet {
+            location: "s3://bucket/events/*.parquet".into(),
+        };
+        assert_eq!(
+            parquet.create_view_sql("events"),
+            [
+                "CREATE OR REPLACE VIEW \"events\" AS SELECT * FROM read_parquet('s3://bucket/events/*.parquet')"
+            ]
+        );
+
+        let postgres = ViewSource::Postgres {
+            connection: "host=db dbname=app".into(),
+            schema: "public".into(),
+            table: "users".into(),
+        };
+        let sql = postgres.create_view_sql("users");
+        assert_eq!(
+            sql[2],
+            "ATTACH IF NOT EXISTS 'host=db dbname=app' AS \"pg_users\" (TYPE postgres, READ_ONLY)"
+        );
+        assert_eq!(
+            sql[3],
+            "CREATE OR REPLACE VIEW \"users\" AS SELECT * FROM \"pg_users\".\"public\".\"users\""
+        );
+    }
+
+    #[test]
+    fn refresh_is_a_no_op_for_views() {
+        assert_eq!(
+            refresh_sql("events", Materialization::View, "SELECT 1"),
+            None
+        );
+        assert_eq!(
+            refresh_sql("events", Materialization::Table, "SELECT 1").unwrap(),
+            "CREATE OR REPLACE TABLE \"events\" AS SELECT 1"
+        );
+    }
+
+    #[test]
+    fn writes_partitions_and_exposes_them_as_a_view() {
+        let layout = PartitionedLayout {
+            directory: PathBuf::from("/data/karna/events"),
+            partition_by: vec!["year".into(), "month".into()],
+            parquet: ParquetOptions::default(),
+        };
+        assert_eq!(
+            layout
+                .partition_dir(&[Some("2024".into()), Some("05".into())])
+                .unwrap(),
+            PathBuf::from("/data/karna/events/year=2024/month=05")
+        );
+        assert_eq!(
+            layout
+                .partition_dir(&[Some("20 24%".into()), None])
+                .unwrap(),
+            PathBuf::from("/data/karna/events/year=20%2024%25/month=__HIVE_DEFAULT_PARTITION__")
+        );
+        for bad in ["..", ".", "../../x", "a/b", "a\\b", "a\0"] {
+            assert!(
+                matches!(
+                    layout.partition_dir(&[Some(bad.into()), None]),
+                    Err(Error::InvalidPartition(_))
+                ),
+                "{bad:?}"
+            );
+        }
+        assert_eq!(
+            layout.view_sql("events"),
+            "CREATE OR REPLACE VIEW \"events\" AS SELECT * FROM \
+             read_parquet('/data/karna/events/**/*.parquet', hive_partitioning = true)"
+        );
+        assert_eq!(
+            layout.write_sql("SELECT * FROM staging", &[vec![Some("2024".into()), None]]),
+            "COPY (SELECT * FROM (SELECT * FROM staging) WHERE \
+             (CAST(\"year\" AS VARCHAR) = '2024' AND \"month\" IS NULL)) \
+             TO '/data/karna/events' (FORMAT parquet, COMPRESSION zstd, ROW_GROUP_SIZE 122880, PARTITION_BY (\"year\", \"month\"), \
+             OVERWRITE_OR_IGNORE, FILENAME_PATTERN 'data_{uuid}')"
+        );
+        assert_eq!(
+            layout.write_sql("SELECT * FROM staging", &[]),
+            "COPY (SELECT * FROM staging) TO '/data/karna/events' \
+             (FORMAT parquet, COMPRESSION zstd, ROW_GROUP_SIZE 122880, PARTITION_BY (\"year\", \"month\"), OVERWRITE)"
+        );
+    }
+
+    #[test]
+    fn optimize_rewrites_with_configured_codec() {
+        let options = ParquetOptions {
+            compression: Compression::Snappy,
+            row_group_size: 1_000_000,
+        };
+        assert_eq!(
+            options.rewrite_sql(
+                "/data/sales/*.parquet",
+                Path::new("/data/sales.tmp.parquet")
+            ),
+            "COPY (SELECT * FROM read_parquet('/data/sales/*.parquet')) TO '/data/sales.tmp.parquet' \
+             (FORMAT parquet, COMPRESSION snappy, ROW_GROUP_SIZE 1000000)"
+        );
+        let report = SizeReport {
+            before_bytes: 1000,
+            after_bytes: 600,
+        };
+        assert_eq!(report.saved_bytes(), 400);
+        assert_eq!(report.saved_fraction(), 0.4);
+    }
+
+    #[test]
+    fn sums_file_and_partition_tree_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let partition = dir.path().join("year=2024");
+        fs::create_dir(&partition).unwrap();
+        fs::write(dir.path().join("a.parquet"), [0u8; 10]).unwrap();
+        fs::write(partition.join("b.parquet"), [0u8; 32]).unwrap();
+
+        assert_eq!(storage_size(&partition.join("b.parquet")).unwrap(), 32);
+        assert_eq!(storage_size(dir.path()).unwrap(), 42);
+        assert!(storage_size(&dir.path().join("missing")).is_err());
+
+        std::os::unix::fs::symlink(dir.path(), partition.join("loop")).unwrap();
+        assert_eq!(storage_size(dir.path()).unwrap(), 42);
+    }
+
+    #[test]
+    fn archives_to_parquet_and_back() {
+        let archive = Archive {
+            dataset: "logs_2019".into(),
+            catalog: "ds_logs_2019".into(),
+            database: PathBuf::from("/data/karna/logs_2019.db"),
+            parquet: PathBuf::from("/data/karna/archive/logs_2019.parquet"),
+        };
+        assert_eq!(
+            archive.archive_sql(&ParquetOptions::default()),
+            [
+                "COPY (SELECT * FROM \"ds_logs_2019\".\"logs_2019\") TO '/data/karna/archive/logs_2019.parquet' \
+                 (FORMAT parquet, COMPRESSION zstd, ROW_GROUP_SIZE 122880)",
+                "DETACH \"ds_logs_2019\"",
+                "CREATE OR REPLACE VIEW \"logs_2019\" AS SELECT * FROM \
+                 read_parquet('/data/karna/archive/logs_2019.parquet')",
+            ]
+        );
+        assert_eq!(
+            archive.unarchive_sql(),
+            [
+                "ATTACH '/data/karna/logs_2019.db' AS \"ds_logs_2019\"",
+                "CREATE TABLE \"ds_logs_2019\".\"logs_2019\" AS SELECT * FROM \
+                 read_parquet('/data/karna/archive/logs_2019.parquet')",
+                "DROP VIEW IF EXISTS \"logs_2019\"",
+            ]
+        );
+    }
+}
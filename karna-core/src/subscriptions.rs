@@ -0,0 +1,130 @@
+//! Users watching datasets, and the notifications a dataset change fans out to.
+
+use chrono::{DateTime, Utc};
+
+/// Something that happened to a dataset that watchers may want to hear about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatasetEvent {
+    Refreshed,
+    Appended {
+        rows: u64,
+    },
+    /// Names of the quality checks that failed.
+    ChecksFailed(Vec<String>),
+}
+
+impl DatasetEvent {
+    fn describe(&self, dataset: &str) -> String {
+        match self {
+            DatasetEvent::Refreshed => format!("{dataset} was refreshed"),
+            DatasetEvent::Appended { rows } => format!("{rows} rows were appended to {dataset}"),
+            DatasetEvent::ChecksFailed(checks) => format!(
+                "{} quality check(s) failed on {dataset}: {}",
+                checks.len(),
+                checks.join(", ")
+            ),
+        }
+    }
+}
+
+/// A user watching a dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub user: String,
+    pub dataset: String,
+    /// Also deliver by email, not only in the app.
+    pub email: bool,
+    /// Only notify when quality checks fail, not on every refresh or append.
+    pub failures_only: bool,
+}
+
+impl Subscription {
+    fn wants(&self, event: &DatasetEvent) -> bool {
+        !self.failures_only || matches!(event, DatasetEvent::ChecksFailed(_))
+    }
+}
+
+/// A notification addressed to one user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub user: String,
+    pub dataset: String,
+    pub message: String,
+    pub email: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Notifications for every subscriber of `dataset` interested in `event`.
+///
+/// `actor` is the user who caused the change, if any; they aren't told about it.
+pub fn notifications_for(
+    subscriptions: &[Subscription],
+    dataset: &str,
+    event: &DatasetEvent,
+    actor: Option<&str>,
+    now: DateTime<Utc>,
+) -> Vec<Notification> {
+    let message = event.describe(dataset);
+    subscriptions
+        .iter()
+        .filter(|s| s.dataset == dataset && s.wants(event) && Some(s.user.as_str()) != actor)
+        .map(|s| Notification {
+            user: s.user.clone(),
+            dataset: dataset.to_string(),
+            message: message.clone(),
+            email: s.email,
+            created_at: now,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(user: &str, dataset: &str, email: bool, failures_only: bool) -> Subscription {
+        Subscription {
+            user: user.into(),
+            dataset: dataset.into(),
+            email,
+            failures_only,
+        }
+    }
+
+    #[test]
+    fn fans_out_to_interested_watchers() {
+        let subscriptions = [
+            subscription("ana", "sales", true, false),
+            subscription("bo", "sales", false, true),
+            subscription("cy", "sales", false, false),
+            subscription("ana", "orders", false, false),
+        ];
+        let now = Utc::now();
+
+        let appended = notifications_for(
+            &subscriptions,
+            "sales",
+            &DatasetEvent::Appended { rows: 12 },
+            Some("cy"),
+            now,
+        );
+        assert_eq!(appended.len(), 1);
+        assert_eq!(appended[0].user, "ana");
+        assert!(appended[0].email);
+        assert_eq!(appended[0].message, "12 rows were appended to sales");
+
+        let failed = notifications_for(
+            &subscriptions,
+            "sales",
+            &DatasetEvent::ChecksFailed(vec!["not_null(id)".into()]),
+            None,
+            now,
+        );
+        let users = failed.iter().map(|n| n.user.as_str()).collect::<Vec<_>>();
+        assert_eq!(users, ["ana", "bo", "cy"]);
+        assert_eq!(
+            failed[1].message,
+            "1 quality check(s) failed on sales: not_null(id)"
+        );
+    }
+}
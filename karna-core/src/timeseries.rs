@@ -0,0 +1,85 @@
+//! Time-series resampling over a dataset's timestamp column.
+
+use crate::sql::quote_ident;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Hour,
+    Day,
+    Week,
+}
+
+impl Bucket {
+    fn interval(self) -> &'static str {
+        match self {
+            Bucket::Hour => "INTERVAL 1 HOUR",
+            Bucket::Day => "INTERVAL 1 DAY",
+            // DuckDB aligns weekly buckets to Mondays.
+            Bucket::Week => "INTERVAL 7 DAY",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregation {
+    fn function(self) -> &'static str {
+        match self {
+            Aggregation::Count => "count",
+            Aggregation::Sum => "sum",
+            Aggregation::Avg => "avg",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resample {
+    pub timestamp_column: String,
+    pub value_column: String,
+    pub aggregation: Aggregation,
+    pub bucket: Bucket,
+}
+
+impl Resample {
+    /// Query returning one `bucket`, `value` row per non-empty bucket, in time order.
+    pub fn sql(&self, table: &str) -> String {
+        let ts = quote_ident(&self.timestamp_column);
+        format!(
+            "SELECT time_bucket({}, {ts}) AS bucket, {}({}) AS value FROM {} \
+             WHERE {ts} IS NOT NULL GROUP BY bucket ORDER BY bucket",
+            self.bucket.interval(),
+            self.aggregation.function(),
+            quote_ident(&self.value_column),
+            quote_ident(table),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_values_by_day() {
+        let resample = Resample {
+            timestamp_column: "created_at".into(),
+            value_column: "amount".into(),
+            aggregation: Aggregation::Sum,
+            bucket: Bucket::Day,
+        };
+        assert_eq!(
+            resample.sql("orders"),
+            "SELECT time_bucket(INTERVAL 1 DAY, \"created_at\") AS bucket, sum(\"amount\") AS value \
+             FROM \"orders\" WHERE \"created_at\" IS NOT NULL GROUP BY bucket ORDER BY bucket"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Built-in cleanup transforms that rewrite a dataset table in place.
+
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+
+use crate::error::{Error, Result};
+use crate::sql::{quote_ident, quote_literal};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transform {
+    /// Keep one row per key: the first by `order_by` (ascending) when given,
+    /// otherwise an arbitrary one. With no keys, drop exact duplicate rows.
+    DropDuplicates {
+        keys: Vec<String>,
+        order_by: Option<String>,
+    },
+    TrimWhitespace {
+        columns: Vec<String>,
+    },
+    DropColumns {
+        columns: Vec<String>,
+    },
+    CastColumn {
+        column: String,
+        data_type: String,
+    },
+    /// Replace NULLs in `column` with `value`, which DuckDB casts to the column type.
+    FillNulls {
+        column: String,
+        value: String,
+    },
+}
+
+fn ident_list(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Position of each row among those sharing `keys`, ordered by `order_by`.
+fn row_number(keys: &[String], order_by: Option<&str>) -> String {
+    let order = order_by
+        .map(|column| format!(" ORDER BY {}", quote_ident(column)))
+        .unwrap_or_default();
+    format!(
+        "row_number() OVER (PARTITION BY {}{order})",
+        ident_list(keys)
+    )
+}
+
+/// Checks that `data_type` is a single DuckDB type name, so it can be spliced into a cast.
+fn validate_data_type(data_type: &str) -> Result<()> {
+    let invalid = || Error::InvalidTransform(format!("unknown data type `{data_type}`"));
+    let mut parser = Parser::new(&DuckDbDialect {})
+        .try_with_sql(data_type)
+        .map_err(|_| invalid())?;
+    parser.parse_data_type().map_err(|_| invalid())?;
+    if parser.peek_token().token != Token::EOF {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+impl Transform {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Transform::TrimWhitespace { columns } | Transform::DropColumns { columns }
+                if columns.is_empty() =>
+            {
+                Err(Error::InvalidTransform("no columns selected".to_string()))
+            }
+            Transform::CastColumn { data_type, .. } => validate_data_type(data_type),
+            _ => Ok(()),
+        }
+    }
+
+    /// `SELECT` producing the transformed rows of `table`.
+    pub fn select_sql(&self, table: &str) -> Result<String> {
+        self.validate()?;
+        let table = quote_ident(table);
+        Ok(match self {
+            Transform::DropDuplicates { keys, .. } if keys.is_empty() => {
+                format!("SELECT DISTINCT * FROM {table}")
+            }
+            Transform::DropDuplicates { keys, order_by } => format!(
+                "SELECT * FROM {table} QUALIFY {} = 1",
+                row_number(keys, order_by.as_deref())
+            ),
+            Transform::TrimWhitespace { columns } => {
+                let replacements = columns
+                    .iter()
+                    .map(|c| {
+                        let c = quote_ident(c);
+                        format!("trim({c}) AS {c}")
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "SELECT * REPLACE ({}) FROM {table}",
+                    replacements.join(", ")
+                )
+            }
+            Transform::DropColumns { columns } => {
+                format!("SELECT * EXCLUDE ({}) FROM {table}", ident_list(columns))
+            }
+            Transform::CastColumn { column, data_type } => {
+                let c = quote_ident(column);
+                format!("SELECT * REPLACE (CAST({c} AS {data_type}) AS {c}) FROM {table}")
+            }
+            Transform::FillNulls { column, value } => {
+                let c = quote_ident(column);
+                let value = quote_literal(value);
+                format!("SELECT * REPLACE (coalesce({c}, {value}) AS {c}) FROM {table}")
+            }
+        })
+    }
+
+    /// Statement that replaces `table` with its transformed rows.
+    pub fn apply_sql(&self, table: &str) -> Result<String> {
+        Ok(format!(
+            "CREATE OR REPLACE TABLE {} AS {}",
+            quote_ident(table),
+            self.select_sql(table)?
+        ))
+    }
+
+    /// Sample of the rows the transform would change or remove, for review before applying.
+    ///
+    /// For casts this lists the rows whose values would fail to convert.
+    pub fn preview_sql(&self, table: &str, limit: u32) -> Result<String> {
+        self.validate()?;
+        let table = quote_ident(table);
+        let sql = match self {
+            Transform::DropDuplicates { keys, .. } if keys.is_empty() => format!(
+                "SELECT * FROM (SELECT * FROM {table} EXCEPT ALL SELECT DISTINCT * FROM {table})"
+            ),
+            Transform::DropDuplicates { keys, order_by } => format!(
+                "SELECT * FROM {table} QUALIFY {} > 1",
+                row_number(keys, order_by.as_deref())
+            ),
+            Transform::TrimWhitespace { columns } => {
+                let changed = columns
+                    .iter()
+                    .map(|c| {
+                        let c = quote_ident(c);
+                        format!("{c} <> trim({c})")
+                    })
+                    .collect::<Vec<_>>();
+                format!("SELECT * FROM {table} WHERE {}", changed.join(" OR "))
+            }
+            Transform::DropColumns { columns } => {
+                format!("SELECT {} FROM {table}", ident_list(columns))
+            }
+            Transform::CastColumn { column, data_type } => {
+                let c = quote_ident(column);
+                format!(
+                    "SELECT * FROM {table} WHERE {c} IS NOT NULL AND TRY_CAST({c} AS {data_type}) IS NULL"
+                )
+            }
+            Transform::FillNulls { column, .. } => {
+                format!(
+                    "SELECT * FROM {table} WHERE {} IS NULL",
+                    quote_ident(column)
+                )
+            }
+        };
+        Ok(format!("{sql} LIMIT {limit}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedupes_by_key_via_ctas() {
+        let transform = Transform::DropDuplicates {
+            keys: vec!["id".into()],
+            order_by: None,
+        };
+        assert_eq!(
+            transform.apply_sql("orders").unwrap(),
+            "CREATE OR REPLACE TABLE \"orders\" AS SELECT * FROM \"orders\" \
+             QUALIFY row_number() OVER (PARTITION BY \"id\") = 1"
+        );
+        assert_eq!(
+            transform.preview_sql("orders", 20).unwrap(),
+            "SELECT * FROM \"orders\" QUALIFY row_number() OVER (PARTITION BY \"id\") > 1 LIMIT 20"
+        );
+
+        let earliest = Transform::DropDuplicates {
+            keys: vec!["id".into()],
+            order_by: Some("updated at".into()),
+        };
+        assert_eq!(
+            earliest.apply_sql("orders").unwrap(),
+            "CREATE OR REPLACE TABLE \"orders\" AS SELECT * FROM \"orders\" \
+             QUALIFY row_number() OVER (PARTITION BY \"id\" ORDER BY \"updated at\") = 1"
+        );
+    }
+
+    #[test]
+    fn fills_nulls_with_quoted_literal() {
+        let transform = Transform::FillNulls {
+            column: "city".into(),
+            value: "O'Fallon".into(),
+        };
+        assert_eq!(
+            transform.select_sql("people").unwrap(),
+            "SELECT * REPLACE (coalesce(\"city\", 'O''Fallon') AS \"city\") FROM \"people\""
+        );
+    }
+
+    #[test]
+    fn rejects_bad_cast_types_and_empty_column_lists() {
+        let cast = Transform::CastColumn {
+            column: "amount".into(),
+            data_type: "INTEGER); DROP TABLE x; --".into(),
+        };
+        assert!(matches!(
+            cast.apply_sql("t"),
+            Err(Error::InvalidTransform(_))
+        ));
+        let cast = Transform::CastColumn {
+            column: "amount".into(),
+            data_type: "DECIMAL(10, 2)".into(),
+        };
+        assert!(cast.apply_sql("t").is_ok());
+        let drop = Transform::DropColumns { columns: vec![] };
+        assert!(drop.select_sql("t").is_err());
+    }
+}
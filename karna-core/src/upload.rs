@@ -0,0 +1,242 @@
+//! Handling of uploaded files before they are ingested.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+/// Free space required per uploaded byte: the saved upload, the materialized
+/// table and DuckDB's spill files can all exist at once.
+pub const SPACE_HEADROOM: u64 = 3;
+
+/// Fails unless `available` bytes leave room to ingest an upload of `upload_bytes`.
+pub fn ensure_space(available: u64, upload_bytes: u64) -> Result<()> {
+    let required = upload_bytes.saturating_mul(SPACE_HEADROOM);
+    if available < required {
+        return Err(Error::InsufficientStorage {
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Checks the filesystem holding `storage_dir` before an upload is saved, so
+/// ingestion fails up front instead of running out of space mid-write.
+pub fn check_free_space(storage_dir: &Path, upload_bytes: u64) -> Result<()> {
+    ensure_space(fs4::available_space(storage_dir)?, upload_bytes)
+}
+
+const STAGING_PREFIX: &str = "upload-";
+
+static NEXT_STAGING_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Directory holding uploads while they are being saved and ingested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadStaging {
+    root: PathBuf,
+}
+
+impl UploadStaging {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    /// Creates a fresh directory for one upload.
+    pub fn stage(&self) -> Result<StagedUpload> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let id = NEXT_STAGING_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = self.root.join(format!(
+            "{STAGING_PREFIX}{}-{nanos}-{id}",
+            std::process::id()
+        ));
+        fs::create_dir(&dir)?;
+        Ok(StagedUpload { dir, keep: false })
+    }
+
+    /// Removes staging directories last modified at least `max_age` ago,
+    /// left behind by a crash or kill, along with stray files named like them.
+    /// Returns how many were removed.
+    pub fn sweep(&self, max_age: Duration) -> Result<usize> {
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if !entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(STAGING_PREFIX)
+            {
+                continue;
+            }
+            // Another sweep may have removed the entry since it was listed.
+            let metadata = match entry.metadata() {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                metadata => metadata?,
+            };
+            if now.duration_since(metadata.modified()?).unwrap_or_default() < max_age {
+                continue;
+            }
+            let result = if metadata.is_dir() {
+                fs::remove_dir_all(entry.path())
+            } else {
+                fs::remove_file(entry.path())
+            };
+            match result {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                result => {
+                    result?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// A staging directory that is deleted when dropped, so every error path
+/// cleans up after itself.
+#[derive(Debug)]
+pub struct StagedUpload {
+    dir: PathBuf,
+    keep: bool,
+}
+
+impl StagedUpload {
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path for a file saved into this upload's directory.
+    pub fn file(&self, name: &str) -> PathBuf {
+        let name = Path::new(name).file_name().unwrap_or("upload".as_ref());
+        self.dir.join(name)
+    }
+
+    /// Keeps the directory on drop, leaving its removal to the caller.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.dir.clone()
+    }
+}
+
+impl Drop for StagedUpload {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// Runs `ingest` over every upload with at most `parallelism` running at once.
+///
+/// Each worker should open its own DuckDB connection. Results come back in the
+/// order of `uploads`, so one failed file doesn't hide the others' status.
+pub fn ingest_concurrently<T, R, F>(uploads: Vec<T>, parallelism: usize, ingest: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let total = uploads.len();
+    let queue = Mutex::new(uploads.into_iter().enumerate());
+    let results = Mutex::new((0..total).map(|_| None).collect::<Vec<Option<R>>>());
+    thread::scope(|scope| {
+        for _ in 0..parallelism.clamp(1, total.max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some((index, upload)) = next else { break };
+                    let result = ingest(upload);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every upload is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires_headroom_over_upload_size() {
+        assert!(ensure_space(300, 100).is_ok());
+        assert!(matches!(
+            ensure_space(299, 100),
+            Err(Error::InsufficientStorage {
+                required: 300,
+                available: 299
+            })
+        ));
+        assert!(check_free_space(Path::new("/definitely/not/here"), 1).is_err());
+    }
+
+    #[test]
+    fn staged_uploads_clean_up_on_drop() {
+        let root = tempfile::tempdir().unwrap();
+        let staging = UploadStaging::new(root.path()).unwrap();
+
+        let first = staging.stage().unwrap();
+        let second = staging.stage().unwrap();
+        assert_ne!(first.dir(), second.dir());
+        let file = first.file("../../etc/sales.csv");
+        assert_eq!(file, first.dir().join("sales.csv"));
+        fs::write(&file, "id\n1\n").unwrap();
+
+        let dir = first.dir().to_path_buf();
+        drop(first);
+        assert!(!dir.exists());
+
+        let kept = second.keep();
+        assert!(kept.exists());
+        assert_eq!(staging.sweep(Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(staging.sweep(Duration::ZERO).unwrap(), 1);
+        assert!(!kept.exists());
+    }
+
+    #[test]
+    fn sweeps_stray_files_with_the_staging_prefix() {
+        let root = tempfile::tempdir().unwrap();
+        let staging = UploadStaging::new(root.path()).unwrap();
+        let stray = root.path().join(format!("{STAGING_PREFIX}stray"));
+        fs::write(&stray, "partial").unwrap();
+        let kept = staging.stage().unwrap().keep();
+
+        assert_eq!(staging.sweep(Duration::ZERO).unwrap(), 2);
+        assert!(!stray.exists());
+        assert!(!kept.exists());
+    }
+
+    #[test]
+    fn ingests_with_bounded_parallelism() {
+        let running = AtomicU64::new(0);
+        let peak = AtomicU64::new(0);
+        let results = ingest_concurrently((0..8).collect(), 3, |n: u64| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+            if n == 5 { Err(n) } else { Ok(n * 10) }
+        });
+        assert_eq!(results.len(), 8);
+        assert_eq!(results[1], Ok(10));
+        assert_eq!(results[5], Err(5));
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+}
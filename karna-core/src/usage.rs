@@ -0,0 +1,204 @@
+//! Per-dataset query usage rollups.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::analysis::referenced_tables;
+use crate::error::Result;
+
+/// How often and how recently a dataset has been queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatasetUsage {
+    pub query_count: u64,
+    pub last_queried_at: DateTime<Utc>,
+}
+
+/// Usage rollup keyed by lowercased table name.
+#[derive(Debug, Clone, Default)]
+pub struct UsageStats {
+    datasets: HashMap<String, DatasetUsage>,
+}
+
+impl UsageStats {
+    /// Records an executed query against every table it references.
+    pub fn record(&mut self, sql: &str, executed_at: DateTime<Utc>) -> Result<()> {
+        for table in referenced_tables(sql)? {
+            self.datasets
+                .entry(table)
+                .and_modify(|usage| {
+                    usage.query_count += 1;
+                    usage.last_queried_at = usage.last_queried_at.max(executed_at);
+                })
+                .or_insert(DatasetUsage {
+                    query_count: 1,
+                    last_queried_at: executed_at,
+                });
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, table: &str) -> Option<&DatasetUsage> {
+        self.datasets.get(&table.to_lowercase())
+    }
+
+    /// Table names ordered from most to least recently queried.
+    pub fn recently_used(&self) -> Vec<&str> {
+        let mut tables = self.datasets.iter().collect::<Vec<_>>();
+        tables.sort_by(|(a_name, a), (b_name, b)| {
+            b.last_queried_at
+                .cmp(&a.last_queried_at)
+                .then_with(|| a_name.cmp(b_name))
+        });
+        tables.into_iter().map(|(name, _)| name.as_str()).collect()
+    }
+}
+
+/// A dataset's footprint on disk, as input to [`storage_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetStorage {
+    pub name: String,
+    /// The dataset's `.db` file, parquet file or partition directory.
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub created_at: DateTime<Utc>,
+    pub archived: bool,
+}
+
+/// One row of the storage report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+    pub name: String,
+    pub size_bytes: u64,
+    /// `None` if the dataset has never been queried.
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    /// Not queried for `cold_after` and not archived yet.
+    pub archive_candidate: bool,
+}
+
+/// Datasets from largest to smallest with their last access, flagging those
+/// unused for `cold_after` as archive candidates.
+///
+/// Never-queried datasets count as accessed when they were created.
+pub fn storage_report(
+    datasets: &[DatasetStorage],
+    usage: &UsageStats,
+    now: DateTime<Utc>,
+    cold_after: Duration,
+) -> Vec<StorageEntry> {
+    let mut entries = datasets
+        .iter()
+        .map(|dataset| {
+            let last_accessed_at = usage.get(&dataset.name).map(|u| u.last_queried_at);
+            let idle_since = last_accessed_at.unwrap_or(dataset.created_at);
+            StorageEntry {
+                name: dataset.name.clone(),
+                size_bytes: dataset.size_bytes,
+                last_accessed_at,
+                archive_candidate: !dataset.archived && now - idle_since >= cold_after,
+            }
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    entries
+}
+
+/// Files in the storage directory that belong to no dataset.
+pub fn orphaned_files<'a>(files: &'a [PathBuf], datasets: &[DatasetStorage]) -> Vec<&'a Path> {
+    files
+        .iter()
+        .filter(|file| !datasets.iter().any(|d| file.starts_with(&d.path)))
+        .map(PathBuf::as_path)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn rolls_up_counts_and_recency() {
+        let t1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let t2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let mut stats = UsageStats::default();
+        stats
+            .record("SELECT * FROM sales JOIN stores USING (id)", t1)
+            .unwrap();
+        stats.record("SELECT count(*) FROM Sales", t2).unwrap();
+
+        assert_eq!(
+            stats.get("sales"),
+            Some(&DatasetUsage {
+                query_count: 2,
+                last_queried_at: t2
+            })
+        );
+        assert_eq!(stats.get("stores").unwrap().query_count, 1);
+        assert_eq!(stats.recently_used(), ["sales", "stores"]);
+    }
+
+    #[test]
+    fn counts_tables_shadowed_by_same_named_ctes() {
+        let mut stats = UsageStats::default();
+        stats
+            .record(
+                "WITH sales AS (SELECT * FROM sales WHERE amount > 0) SELECT * FROM sales",
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(stats.get("sales").unwrap().query_count, 1);
+    }
+
+    #[test]
+    fn reports_largest_datasets_and_cold_candidates() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let dataset = |name: &str, size_bytes, days_old, archived| DatasetStorage {
+            name: name.into(),
+            path: PathBuf::from(format!("/data/{name}.db")),
+            size_bytes,
+            created_at: now - Duration::days(days_old),
+            archived,
+        };
+        let datasets = [
+            dataset("events", 500, 400, false),
+            dataset("sales", 900, 400, false),
+            dataset("old_logs", 100, 400, true),
+            dataset("fresh", 10, 1, false),
+        ];
+        let mut usage = UsageStats::default();
+        usage
+            .record("SELECT * FROM sales", now - Duration::days(2))
+            .unwrap();
+
+        let report = storage_report(&datasets, &usage, now, Duration::days(90));
+        let summary = report
+            .iter()
+            .map(|e| (e.name.as_str(), e.archive_candidate))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("sales", false),
+                ("events", true),
+                ("old_logs", false),
+                ("fresh", false)
+            ]
+        );
+        assert_eq!(report[0].last_accessed_at, Some(now - Duration::days(2)));
+
+        let files = [
+            PathBuf::from("/data/sales.db"),
+            PathBuf::from("/data/tmp_import.db"),
+        ];
+        assert_eq!(
+            orphaned_files(&files, &datasets),
+            [Path::new("/data/tmp_import.db")]
+        );
+    }
+}
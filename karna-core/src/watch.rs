@@ -0,0 +1,111 @@
+//! Drop folders: local directories polled for new files to ingest.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::error::{Error, Result};
+
+/// Where each new file in a watched folder is ingested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchTarget {
+    /// Append every file to one dataset.
+    Append { dataset: String },
+    /// Create a dataset per file, named after the file stem.
+    DatasetPerFile,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedFolder {
+    pub directory: PathBuf,
+    /// Glob matched against file names, e.g. `orders_*.csv`.
+    pub pattern: String,
+    pub target: WatchTarget,
+    /// Files modified more recently than this may still be being written and
+    /// are left for the next poll.
+    pub settle: Duration,
+}
+
+/// Provenance recorded for each file ingested from a watched folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified: SystemTime,
+}
+
+impl WatchedFolder {
+    /// Matching files not in `seen` that have settled, oldest first.
+    pub fn poll(&self, seen: &BTreeSet<PathBuf>) -> Result<Vec<SourceFile>> {
+        let pattern = glob::Pattern::new(&self.pattern)
+            .map_err(|e| Error::InvalidSource(format!("invalid glob `{}`: {e}", self.pattern)))?;
+        let now = SystemTime::now();
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let path = entry.path();
+            if !metadata.is_file()
+                || seen.contains(&path)
+                || !pattern.matches(&entry.file_name().to_string_lossy())
+            {
+                continue;
+            }
+            let modified = metadata.modified()?;
+            if now.duration_since(modified).unwrap_or_default() < self.settle {
+                continue;
+            }
+            files.push(SourceFile {
+                path,
+                size_bytes: metadata.len(),
+                modified,
+            });
+        }
+        files.sort_by(|a, b| (a.modified, &a.path).cmp(&(b.modified, &b.path)));
+        Ok(files)
+    }
+
+    /// Dataset a new file is ingested into.
+    pub fn dataset_for(&self, file: &Path) -> String {
+        match &self.target {
+            WatchTarget::Append { dataset } => dataset.clone(),
+            WatchTarget::DatasetPerFile => file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polls_for_unseen_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("orders_1.csv"), "id\n1\n").unwrap();
+        fs::write(dir.path().join("orders_2.csv"), "id\n2\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "skip").unwrap();
+
+        let folder = WatchedFolder {
+            directory: dir.path().to_path_buf(),
+            pattern: "orders_*.csv".into(),
+            target: WatchTarget::DatasetPerFile,
+            settle: Duration::ZERO,
+        };
+        let seen = BTreeSet::from([dir.path().join("orders_1.csv")]);
+        let new = folder.poll(&seen).unwrap();
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].path, dir.path().join("orders_2.csv"));
+        assert_eq!(new[0].size_bytes, 5);
+        assert_eq!(folder.dataset_for(&new[0].path), "orders_2");
+
+        let settling = WatchedFolder {
+            settle: Duration::from_secs(3600),
+            ..folder
+        };
+        assert!(settling.poll(&BTreeSet::new()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+//! Encryption of sensitive values, such as connection strings and signing
+//! keys, before they are stored in the metadata database.
+//!
+//! Stored values look like `enc:v2:<key id>:<base64 nonce + ciphertext>`, so
+//! old values stay readable after the active key is rotated. The key id is
+//! authenticated along with the ciphertext; `enc:v1:` values, written before
+//! it was, are still read and are upgraded by [`Keyring::rotate`].
+
+use std::collections::BTreeMap;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::error::{Error, Result};
+
+const PREFIX: &str = "enc:v2:";
+/// Values encrypted without the key id as associated data.
+const PREFIX_V1: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+/// Encryption keys by id: the active key encrypts, every key decrypts.
+#[derive(Clone)]
+pub struct Keyring {
+    active: String,
+    keys: BTreeMap<String, Aes256Gcm>,
+}
+
+impl std::fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("active", &self.active)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn cipher(id: &str, key_base64: &str) -> Result<Aes256Gcm> {
+    // The id is stored between `:` separators in every encrypted value.
+    if id.is_empty() || id.contains(':') {
+        return Err(Error::InvalidKey(id.to_string()));
+    }
+    let key = STANDARD
+        .decode(key_base64.trim())
+        .ok()
+        .filter(|key| key.len() == 32)
+        .ok_or_else(|| Error::InvalidKey(id.to_string()))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+impl Keyring {
+    /// Keyring encrypting with `key_base64`, as read from config or a KMS.
+    pub fn new(id: impl Into<String>, key_base64: &str) -> Result<Self> {
+        let id = id.into();
+        let active = cipher(&id, key_base64)?;
+        Ok(Self {
+            keys: BTreeMap::from([(id.clone(), active)]),
+            active: id,
+        })
+    }
+
+    /// Adds a retired key that is still needed to decrypt older values.
+    pub fn with_retired(mut self, id: impl Into<String>, key_base64: &str) -> Result<Self> {
+        let id = id.into();
+        let retired = cipher(&id, key_base64)?;
+        if self.keys.contains_key(&id) {
+            return Err(Error::DuplicateKey(id));
+        }
+        self.keys.insert(id, retired);
+        Ok(self)
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.keys[&self.active]
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: plaintext.as_bytes(),
+                        aad: self.active.as_bytes(),
+                    },
+                )
+                .map_err(|_| Error::Crypto)?,
+        );
+        Ok(format!(
+            "{PREFIX}{}:{}",
+            self.active,
+            STANDARD.encode(sealed)
+        ))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let (authenticated, id, payload) = split(stored)?;
+        let cipher = self
+            .keys
+            .get(id)
+            .ok_or_else(|| Error::UnknownKey(id.to_string()))?;
+        let sealed = STANDARD.decode(payload).map_err(|_| Error::Malformed)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(Error::Malformed);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let aad = if authenticated { id.as_bytes() } else { &[] };
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| Error::Crypto)?;
+        String::from_utf8(plaintext).map_err(|_| Error::Malformed)
+    }
+
+    /// Whether `stored` was encrypted with a key other than the active one,
+    /// or in the older format.
+    pub fn needs_rotation(&self, stored: &str) -> Result<bool> {
+        let (authenticated, id, _) = split(stored)?;
+        Ok(!authenticated || id != self.active)
+    }
+
+    /// Re-encrypts `stored` with the active key, for a rotation pass over stored values.
+    pub fn rotate(&self, stored: &str) -> Result<String> {
+        self.encrypt(&self.decrypt(stored)?)
+    }
+}
+
+/// Whether a stored value authenticates its key id, the key id, and the payload.
+fn split(stored: &str) -> Result<(bool, &str, &str)> {
+    let (authenticated, rest) = match stored.strip_prefix(PREFIX) {
+        Some(rest) => (true, rest),
+        None => (
+            false,
+            stored.strip_prefix(PREFIX_V1).ok_or(Error::Malformed)?,
+        ),
+    };
+    let (id, payload) = rest.split_once(':').ok_or(Error::Malformed)?;
+    Ok((authenticated, id, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        STANDARD.encode([byte; 32])
+    }
+
+    #[test]
+    fn round_trips_and_rotates_keys() {
+        let old = Keyring::new("2023", &key(1)).unwrap();
+        let stored = old.encrypt("postgres://user:secret@db/app").unwrap();
+        assert!(stored.starts_with("enc:v2:2023:"));
+        assert!(!stored.contains("secret"));
+        assert_ne!(
+            stored,
+            old.encrypt("postgres://user:secret@db/app").unwrap()
+        );
+
+        let rotated = Keyring::new("2024", &key(2))
+            .unwrap()
+            .with_retired("2023", &key(1))
+            .unwrap();
+        assert!(rotated.needs_rotation(&stored).unwrap());
+        let stored = rotated.rotate(&stored).unwrap();
+        assert!(!rotated.needs_rotation(&stored).unwrap());
+        assert_eq!(
+            rotated.decrypt(&stored).unwrap(),
+            "postgres://user:secret@db/app"
+        );
+        assert!(matches!(old.decrypt(&stored), Err(Error::UnknownKey(_))));
+    }
+
+    #[test]
+    fn rejects_bad_keys_and_tampered_values() {
+        assert!(matches!(
+            Keyring::new("short", "c2hvcnQ="),
+            Err(Error::InvalidKey(_))
+        ));
+        for id in ["", "2024:01"] {
+            assert!(matches!(
+                Keyring::new(id, &key(7)),
+                Err(Error::InvalidKey(_))
+            ));
+            assert!(matches!(
+                Keyring::new("k", &key(7))
+                    .unwrap()
+                    .with_retired(id, &key(8)),
+                Err(Error::InvalidKey(_))
+            ));
+        }
+        let keyring = Keyring::new("k", &key(7)).unwrap();
+        let mut stored = keyring.encrypt("hunter2").unwrap();
+        stored.replace_range(stored.len() - 4.., "AAAA");
+        assert!(keyring.decrypt(&stored).is_err());
+        assert!(matches!(
+            keyring.decrypt("plain text"),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_key_ids() {
+        let keyring = Keyring::new("2024", &key(1))
+            .unwrap()
+            .with_retired("2023", &key(2))
+            .unwrap();
+        for id in ["2024", "2023"] {
+            assert!(matches!(
+                keyring.clone().with_retired(id, &key(3)),
+                Err(Error::DuplicateKey(dup)) if dup == id
+            ));
+        }
+    }
+
+    #[test]
+    fn authenticates_the_key_id() {
+        let keyring = Keyring::new("a", &key(1))
+            .unwrap()
+            .with_retired("b", &key(1))
+            .unwrap();
+        let stored = keyring.encrypt("hunter2").unwrap();
+        let relabeled = stored.replacen("enc:v2:a:", "enc:v2:b:", 1);
+        assert!(matches!(keyring.decrypt(&relabeled), Err(Error::Crypto)));
+
+        // Values from before the key id was authenticated still decrypt.
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(keyring.keys["a"].encrypt(&nonce, &b"hunter2"[..]).unwrap());
+        let legacy = format!("enc:v1:a:{}", STANDARD.encode(sealed));
+        assert_eq!(keyring.decrypt(&legacy).unwrap(), "hunter2");
+        assert!(keyring.needs_rotation(&legacy).unwrap());
+        assert!(
+            !keyring
+                .needs_rotation(&keyring.rotate(&legacy).unwrap())
+                .unwrap()
+        );
+    }
+}
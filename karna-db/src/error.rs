@@ -0,0 +1,15 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid encryption key `{0}`: keys are 32 bytes, base64-encoded")]
+    InvalidKey(String),
+    #[error("encryption key id `{0}` is configured more than once")]
+    DuplicateKey(String),
+    #[error("value was encrypted with unknown key `{0}`")]
+    UnknownKey(String),
+    #[error("malformed encrypted value")]
+    Malformed,
+    #[error("failed to encrypt or decrypt value")]
+    Crypto,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
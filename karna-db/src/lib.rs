@@ -1,3 +1,8 @@
+pub mod crypto;
+pub mod error;
+
+pub use error::{Error, Result};
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
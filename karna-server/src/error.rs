@@ -0,0 +1,164 @@
+//! API error codes and the response envelope every JSON endpoint returns.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::json::ConversionError;
+use crate::pagination::InvalidCursor;
+use crate::range::RangeNotSatisfiable;
+use crate::signed_url::InvalidSignature;
+
+/// Machine-readable error codes clients can branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    ValidationFailed,
+    InvalidCursor,
+    InvalidSignature,
+    NotFound,
+    DatasetNotFound,
+    /// Deleting the dataset would break items that read from it.
+    DatasetInUse,
+    /// An upload would create a dataset whose name is taken; `details` lists
+    /// the resolutions the client can retry with.
+    DatasetExists,
+    QueryFailed,
+    QueryTimeout,
+    RangeNotSatisfiable,
+    InsufficientStorage,
+    Internal,
+}
+
+impl ErrorCode {
+    /// HTTP status the code is reported with.
+    pub fn status(self) -> u16 {
+        match self {
+            ErrorCode::BadRequest | ErrorCode::InvalidCursor | ErrorCode::QueryFailed => 400,
+            ErrorCode::InvalidSignature => 403,
+            ErrorCode::ValidationFailed => 422,
+            ErrorCode::NotFound | ErrorCode::DatasetNotFound => 404,
+            ErrorCode::DatasetInUse | ErrorCode::DatasetExists => 409,
+            ErrorCode::RangeNotSatisfiable => 416,
+            ErrorCode::QueryTimeout => 504,
+            ErrorCode::InsufficientStorage => 507,
+            ErrorCode::Internal => 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[error("{message}")]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn status(&self) -> u16 {
+        self.code.status()
+    }
+}
+
+impl From<InvalidCursor> for ApiError {
+    fn from(err: InvalidCursor) -> Self {
+        ApiError::new(ErrorCode::InvalidCursor, err.to_string())
+    }
+}
+
+impl From<ConversionError> for ApiError {
+    fn from(err: ConversionError) -> Self {
+        ApiError::new(ErrorCode::QueryFailed, err.to_string())
+    }
+}
+
+impl From<RangeNotSatisfiable> for ApiError {
+    fn from(err: RangeNotSatisfiable) -> Self {
+        ApiError::new(ErrorCode::RangeNotSatisfiable, err.to_string())
+    }
+}
+
+impl From<InvalidSignature> for ApiError {
+    fn from(err: InvalidSignature) -> Self {
+        ApiError::new(ErrorCode::InvalidSignature, err.to_string())
+    }
+}
+
+/// `{ "data": ..., "error": ... }` body shared by all JSON responses.
+#[derive(Debug, Clone, Serialize)]
+pub struct Envelope<T> {
+    pub data: Option<T>,
+    pub error: Option<ApiError>,
+}
+
+impl<T> Envelope<T> {
+    pub fn ok(data: T) -> Self {
+        Self {
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn err(error: ApiError) -> Self {
+        Self {
+            data: None,
+            error: Some(error),
+        }
+    }
+}
+
+impl<T> From<Result<T, ApiError>> for Envelope<T> {
+    fn from(result: Result<T, ApiError>) -> Self {
+        match result {
+            Ok(data) => Envelope::ok(data),
+            Err(error) => Envelope::err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_error_envelope_with_code() {
+        let error = ApiError::new(ErrorCode::DatasetNotFound, "dataset 7 does not exist")
+            .with_details(json!({ "id": 7 }));
+        assert_eq!(error.status(), 404);
+        assert_eq!(
+            serde_json::to_value(Envelope::<()>::err(error)).unwrap(),
+            json!({
+                "data": null,
+                "error": {
+                    "code": "DATASET_NOT_FOUND",
+                    "message": "dataset 7 does not exist",
+                    "details": { "id": 7 }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn serializes_success_envelope() {
+        assert_eq!(
+            serde_json::to_value(Envelope::ok(vec![1, 2])).unwrap(),
+            json!({ "data": [1, 2], "error": null })
+        );
+    }
+}
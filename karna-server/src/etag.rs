@@ -0,0 +1,64 @@
+//! ETags for conditional `GET`s on dataset resources.
+
+use chrono::{DateTime, Utc};
+
+/// Weak ETag for a single resource, derived from its `updated_at`.
+pub fn weak_etag(updated_at: DateTime<Utc>) -> String {
+    format!("W/\"{:x}\"", updated_at.timestamp_micros())
+}
+
+/// Strong ETag for a dataset's downloadable content at a given version.
+///
+/// Resumed downloads check it through `If-Range`, which only accepts strong tags.
+pub fn content_etag(dataset_id: &str, version: DateTime<Utc>) -> String {
+    format!("\"{dataset_id}-{:x}\"", version.timestamp_micros())
+}
+
+/// Weak ETag for a collection.
+///
+/// The item count is included alongside the newest `updated_at`, so deleting
+/// an item also changes the tag.
+pub fn list_etag(updated_at: impl IntoIterator<Item = DateTime<Utc>>) -> String {
+    let (count, newest) = updated_at
+        .into_iter()
+        .fold((0usize, i64::MIN), |(count, newest), ts| {
+            (count + 1, newest.max(ts.timestamp_micros()))
+        });
+    if count == 0 {
+        return "W/\"0\"".to_string();
+    }
+    format!("W/\"{count:x}-{newest:x}\"")
+}
+
+/// Whether an `If-None-Match` header value matches `etag`, using weak comparison.
+pub fn if_none_match(header: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    header
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn matches_weak_and_strong_forms() {
+        let tag = weak_etag(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+        let strong = tag.trim_start_matches("W/");
+        assert!(if_none_match(&tag, &tag));
+        assert!(if_none_match(&format!("\"other\", {strong}"), &tag));
+        assert!(if_none_match("*", &tag));
+        assert!(!if_none_match("W/\"other\"", &tag));
+    }
+
+    #[test]
+    fn list_etag_changes_when_an_item_is_removed() {
+        let t1 = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let t2 = Utc.with_ymd_and_hms(2024, 5, 2, 0, 0, 0).unwrap();
+        assert_ne!(list_etag([t1, t2]), list_etag([t2]));
+        assert_eq!(list_etag([t1, t2]), list_etag([t2, t1]));
+    }
+}
@@ -0,0 +1,358 @@
+//! Conversion of query result values into JSON for API responses.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+/// A single result value as read from DuckDB.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Boolean(bool),
+    TinyInt(i8),
+    SmallInt(i16),
+    Integer(i32),
+    BigInt(i64),
+    HugeInt(i128),
+    UTinyInt(u8),
+    USmallInt(u16),
+    UInteger(u32),
+    UBigInt(u64),
+    Float(f32),
+    Double(f64),
+    /// Unscaled value and scale: `Decimal { value: 12345, scale: 2 }` is `123.45`.
+    Decimal {
+        value: i128,
+        scale: u8,
+    },
+    Text(String),
+}
+
+/// How numbers JSON can't hold exactly (`HUGEINT`, `DECIMAL`) are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericMode {
+    /// Exact decimal text, e.g. `"123.45"`.
+    #[default]
+    String,
+    /// Nearest `f64`, which may lose digits.
+    Float,
+    /// JSON numbers where no precision is lost, otherwise fail the response.
+    Error,
+}
+
+/// Response options controlling the conversion.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ConversionOptions {
+    pub numeric_mode: NumericMode,
+    /// Write NaN and infinities as `"NaN"`, `"Infinity"` and `"-Infinity"`
+    /// instead of `null`, so they can't be mistaken for SQL `NULL`.
+    pub nan_as_string: bool,
+    /// String written for SQL `NULL` instead of JSON `null`.
+    pub null_marker: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{value} can't be represented exactly as a JSON number")]
+pub struct ConversionError {
+    pub value: String,
+}
+
+/// Type information sent with each result column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnMetadata {
+    pub name: String,
+    pub data_type: String,
+    /// Set for `DECIMAL` columns, so clients can format string values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u8>,
+    /// Set for `UBIGINT` columns: values above `i64::MAX` are sent as strings,
+    /// since many JSON clients can't hold them as numbers.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub large_values_as_string: bool,
+}
+
+impl ColumnMetadata {
+    pub fn new(name: impl Into<String>, data_type: impl Into<String>) -> Self {
+        let data_type = data_type.into();
+        let (precision, scale) = decimal_precision(&data_type).unzip();
+        let large_values_as_string = data_type.trim().eq_ignore_ascii_case("UBIGINT");
+        Self {
+            name: name.into(),
+            data_type,
+            precision,
+            scale,
+            large_values_as_string,
+        }
+    }
+}
+
+/// Precision and scale of a `DECIMAL`/`NUMERIC` type, with DuckDB's default
+/// of `(18, 3)` when none is given.
+fn decimal_precision(data_type: &str) -> Option<(u8, u8)> {
+    let upper = data_type.trim().to_ascii_uppercase();
+    let args = ["DECIMAL", "NUMERIC"]
+        .iter()
+        .find_map(|name| upper.strip_prefix(name))?
+        .trim();
+    if args.is_empty() {
+        return Some((18, 3));
+    }
+    let args = args.strip_prefix('(')?.strip_suffix(')')?;
+    let (precision, scale) = match args.split_once(',') {
+        Some((p, s)) => (p.trim().parse().ok()?, s.trim().parse().ok()?),
+        None => (args.trim().parse().ok()?, 0),
+    };
+    Some((precision, scale))
+}
+
+fn decimal_text(value: i128, scale: u8) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let sign = if value < 0 { "-" } else { "" };
+    let scale = usize::from(scale);
+    if scale == 0 {
+        return format!("{sign}{digits}");
+    }
+    let digits = format!("{digits:0>width$}", width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    format!("{sign}{whole}.{fraction}")
+}
+
+fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// A float column value, with non-finite values written as `options` asks.
+fn float_value(value: f64, options: &ConversionOptions) -> Value {
+    if value.is_finite() || !options.nan_as_string {
+        return float(value);
+    }
+    let text = if value.is_nan() {
+        "NaN"
+    } else if value > 0.0 {
+        "Infinity"
+    } else {
+        "-Infinity"
+    };
+    Value::String(text.to_string())
+}
+
+/// An integer as a JSON number if it fits in 64 bits.
+fn exact_integer(value: i128) -> Option<Value> {
+    i64::try_from(value)
+        .map(Number::from)
+        .or_else(|_| u64::try_from(value).map(Number::from))
+        .ok()
+        .map(Value::Number)
+}
+
+/// A decimal as a JSON number if the `f64` it becomes reads back as the
+/// same value at its scale, e.g. `1.50` but not `12345678901234567.89`.
+fn exact_decimal(text: &str, scale: u8) -> Option<Value> {
+    let float = text.parse::<f64>().ok()?;
+    let scale = usize::from(scale);
+    (format!("{float:.scale$}") == text)
+        .then(|| Number::from_f64(float).map(Value::Number))
+        .flatten()
+}
+
+/// A number JSON can't hold exactly, written as `mode` asks.
+fn wide_number(
+    text: String,
+    exact: Option<Value>,
+    mode: NumericMode,
+) -> Result<Value, ConversionError> {
+    match mode {
+        NumericMode::String => Ok(Value::String(text)),
+        NumericMode::Float => Ok(float(text.parse().unwrap_or(f64::NAN))),
+        NumericMode::Error => exact.ok_or(ConversionError { value: text }),
+    }
+}
+
+pub fn value_to_json(
+    value: &SqlValue,
+    options: &ConversionOptions,
+) -> Result<Value, ConversionError> {
+    Ok(match value {
+        SqlValue::Null => options
+            .null_marker
+            .clone()
+            .map_or(Value::Null, Value::String),
+        SqlValue::Boolean(b) => Value::Bool(*b),
+        SqlValue::TinyInt(n) => Value::from(*n),
+        SqlValue::SmallInt(n) => Value::from(*n),
+        SqlValue::Integer(n) => Value::from(*n),
+        SqlValue::BigInt(n) => Value::from(*n),
+        SqlValue::HugeInt(n) => {
+            return wide_number(n.to_string(), exact_integer(*n), options.numeric_mode);
+        }
+        SqlValue::UTinyInt(n) => Value::from(*n),
+        SqlValue::USmallInt(n) => Value::from(*n),
+        SqlValue::UInteger(n) => Value::from(*n),
+        SqlValue::UBigInt(n) if i64::try_from(*n).is_err() => Value::String(n.to_string()),
+        SqlValue::UBigInt(n) => Value::from(*n),
+        SqlValue::Float(f) => float_value(f64::from(*f), options),
+        SqlValue::Double(f) => float_value(*f, options),
+        SqlValue::Decimal { value, scale } => {
+            let text = decimal_text(*value, *scale);
+            let exact = if *scale == 0 {
+                exact_integer(*value)
+            } else {
+                None
+            };
+            let exact = exact.or_else(|| exact_decimal(&text, *scale));
+            return wide_number(text, exact, options.numeric_mode);
+        }
+        SqlValue::Text(s) => Value::String(s.clone()),
+    })
+}
+
+/// Converts a row, failing on the first value that can't be converted
+/// rather than replacing it with `null`.
+pub fn row_to_json(
+    row: &[SqlValue],
+    options: &ConversionOptions,
+) -> Result<Vec<Value>, ConversionError> {
+    row.iter().map(|v| value_to_json(v, options)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn convert(value: SqlValue, numeric_mode: NumericMode) -> Result<Value, ConversionError> {
+        let options = ConversionOptions {
+            numeric_mode,
+            ..Default::default()
+        };
+        value_to_json(&value, &options)
+    }
+
+    #[test]
+    fn writes_wide_numbers_per_numeric_mode() {
+        let price = || SqlValue::Decimal {
+            value: -5,
+            scale: 3,
+        };
+        assert_eq!(
+            convert(price(), NumericMode::String).unwrap(),
+            json!("-0.005")
+        );
+        assert_eq!(convert(price(), NumericMode::Float).unwrap(), json!(-0.005));
+        assert_eq!(convert(price(), NumericMode::Error).unwrap(), json!(-0.005));
+
+        // DECIMAL(10,2) 1.50 is exact; 20 significant digits aren't.
+        let exact = SqlValue::Decimal {
+            value: 150,
+            scale: 2,
+        };
+        assert_eq!(convert(exact, NumericMode::Error).unwrap(), json!(1.5));
+        let long = SqlValue::Decimal {
+            value: 123_456_789_012_345_678_901,
+            scale: 2,
+        };
+        let err = convert(long, NumericMode::Error).unwrap_err();
+        assert_eq!(err.value, "1234567890123456789.01");
+
+        let big = || SqlValue::HugeInt(i128::from(u64::MAX) + 1);
+        assert_eq!(
+            convert(big(), NumericMode::String).unwrap(),
+            json!("18446744073709551616")
+        );
+        assert!(convert(big(), NumericMode::Error).is_err());
+        assert_eq!(
+            convert(SqlValue::HugeInt(-42), NumericMode::Error).unwrap(),
+            json!(-42)
+        );
+        assert_eq!(
+            convert(SqlValue::Decimal { value: 7, scale: 0 }, NumericMode::Error).unwrap(),
+            json!(7)
+        );
+    }
+
+    #[test]
+    fn propagates_row_conversion_errors() {
+        let options = ConversionOptions {
+            numeric_mode: NumericMode::Error,
+            ..Default::default()
+        };
+        assert_eq!(
+            row_to_json(&[SqlValue::Integer(1), SqlValue::Null], &options).unwrap(),
+            [json!(1), Value::Null]
+        );
+        let err = row_to_json(&[SqlValue::HugeInt(i128::MAX)], &options).unwrap_err();
+        assert_eq!(err.value, i128::MAX.to_string());
+    }
+
+    #[test]
+    fn keeps_unsigned_values_unsigned() {
+        let options = ConversionOptions::default();
+        let row = [
+            SqlValue::UTinyInt(u8::MAX),
+            SqlValue::USmallInt(u16::MAX),
+            SqlValue::UInteger(u32::MAX),
+            SqlValue::UBigInt(i64::MAX as u64),
+            SqlValue::UBigInt(u64::MAX),
+        ];
+        assert_eq!(
+            row_to_json(&row, &options).unwrap(),
+            [
+                json!(255),
+                json!(65535),
+                json!(4294967295u32),
+                json!(i64::MAX),
+                json!("18446744073709551615"),
+            ]
+        );
+        assert!(ColumnMetadata::new("n", "UBIGINT").large_values_as_string);
+        assert!(!ColumnMetadata::new("n", "BIGINT").large_values_as_string);
+    }
+
+    #[test]
+    fn distinguishes_non_finite_floats_from_null() {
+        let row = [
+            SqlValue::Double(f64::NAN),
+            SqlValue::Float(f32::INFINITY),
+            SqlValue::Double(f64::NEG_INFINITY),
+            SqlValue::Double(1.5),
+            SqlValue::Null,
+        ];
+        assert_eq!(
+            row_to_json(&row, &ConversionOptions::default()).unwrap(),
+            [
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                json!(1.5),
+                Value::Null
+            ]
+        );
+        let options: ConversionOptions =
+            serde_json::from_value(json!({ "nan_as_string": true, "null_marker": "NULL" }))
+                .unwrap();
+        assert_eq!(
+            row_to_json(&row, &options).unwrap(),
+            [
+                json!("NaN"),
+                json!("Infinity"),
+                json!("-Infinity"),
+                json!(1.5),
+                json!("NULL"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_decimal_precision_and_scale() {
+        let column = ColumnMetadata::new("price", "DECIMAL(10, 2)");
+        assert_eq!((column.precision, column.scale), (Some(10), Some(2)));
+        assert_eq!(ColumnMetadata::new("p", "NUMERIC").scale, Some(3));
+        assert_eq!(
+            serde_json::to_value(ColumnMetadata::new("id", "BIGINT")).unwrap(),
+            json!({ "name": "id", "data_type": "BIGINT" })
+        );
+    }
+}
@@ -1,3 +1,11 @@
+pub mod error;
+pub mod etag;
+pub mod json;
+pub mod pagination;
+pub mod range;
+pub mod signed_url;
+pub mod validation;
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
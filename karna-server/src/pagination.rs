@@ -0,0 +1,118 @@
+//! Keyset pagination shared by the list endpoints.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid pagination cursor")]
+pub struct InvalidCursor;
+
+/// Position after the last item of a page, ordered by `(created_at, id)` descending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl Cursor {
+    /// Encodes the cursor as an opaque URL-safe token.
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.created_at.timestamp_micros(),
+            self.id
+        ))
+    }
+
+    pub fn decode(token: &str) -> Result<Self, InvalidCursor> {
+        let raw = URL_SAFE_NO_PAD.decode(token).map_err(|_| InvalidCursor)?;
+        let raw = String::from_utf8(raw).map_err(|_| InvalidCursor)?;
+        let (micros, id) = raw.split_once(':').ok_or(InvalidCursor)?;
+        let created_at = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or(InvalidCursor)?;
+        Ok(Self {
+            created_at,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Query parameters accepted by paginated endpoints.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageParams {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+impl PageParams {
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>, InvalidCursor> {
+        self.cursor.as_deref().map(Cursor::decode).transpose()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from a query that fetched up to `limit + 1` rows.
+    ///
+    /// The extra row only signals that another page exists; it is dropped and
+    /// the cursor points at the last row that is returned.
+    pub fn from_overfetch(mut items: Vec<T>, limit: u32, cursor: impl Fn(&T) -> Cursor) -> Self {
+        let limit = limit as usize;
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items.last().map(|item| cursor(item).encode())
+        } else {
+            None
+        };
+        Self { items, next_cursor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn cursor(id: &str) -> Cursor {
+        Cursor {
+            created_at: Utc.with_ymd_and_hms(2024, 3, 1, 8, 30, 0).unwrap(),
+            id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let original = cursor("ds:42");
+        assert_eq!(Cursor::decode(&original.encode()).unwrap(), original);
+        assert!(Cursor::decode("not a cursor").is_err());
+    }
+
+    #[test]
+    fn overfetched_row_produces_next_cursor() {
+        let page = Page::from_overfetch(vec!["a", "b", "c"], 2, |id| cursor(id));
+        assert_eq!(page.items, ["a", "b"]);
+        assert_eq!(page.next_cursor, Some(cursor("b").encode()));
+
+        let last = Page::from_overfetch(vec!["c"], 2, |id| cursor(id));
+        assert_eq!(last.next_cursor, None);
+    }
+}
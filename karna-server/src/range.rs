@@ -0,0 +1,128 @@
+//! `Range` requests on downloads, so interrupted transfers can resume.
+
+/// Inclusive byte range of a file to send with `206 Partial Content`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn content_length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// `Content-Range` header value for a file of `total` bytes.
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{total}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("requested range is outside the {total}-byte file")]
+pub struct RangeNotSatisfiable {
+    pub total: u64,
+}
+
+impl RangeNotSatisfiable {
+    /// `Content-Range` header value sent with the `416` response.
+    pub fn content_range(&self) -> String {
+        format!("bytes */{}", self.total)
+    }
+}
+
+/// Range to serve for a `Range` header on a file of `total` bytes.
+///
+/// `Ok(None)` means the whole file is served: the header is malformed, uses
+/// another unit or asks for several ranges, all of which may be ignored.
+pub fn parse_range(header: &str, total: u64) -> Result<Option<ByteRange>, RangeNotSatisfiable> {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let unsatisfiable = RangeNotSatisfiable { total };
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // `bytes=-500`: the last 500 bytes.
+        (Err(_), Ok(suffix)) if start.is_empty() => {
+            if suffix == 0 || total == 0 {
+                return Err(unsatisfiable);
+            }
+            ByteRange {
+                start: total.saturating_sub(suffix),
+                end: total - 1,
+            }
+        }
+        // `bytes=500-`: everything from byte 500.
+        (Ok(start), Err(_)) if end.is_empty() => ByteRange {
+            start,
+            end: total.saturating_sub(1),
+        },
+        (Ok(start), Ok(end)) if start <= end => ByteRange {
+            start,
+            end: end.min(total.saturating_sub(1)),
+        },
+        _ => return Ok(None),
+    };
+    if range.start >= total {
+        return Err(unsatisfiable);
+    }
+    Ok(Some(range))
+}
+
+/// Whether an `If-Range` header still matches the file, so the range can be
+/// served. Ranges need a strong ETag; weak tags and dates never match, which
+/// falls back to sending the whole, current file.
+pub fn if_range(header: &str, strong_etag: &str) -> bool {
+    let header = header.trim();
+    !header.starts_with("W/") && header == strong_etag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        let range = |header| parse_range(header, 1000).unwrap();
+        assert_eq!(range("bytes=0-499"), Some(ByteRange { start: 0, end: 499 }));
+        assert_eq!(
+            range("bytes=900-"),
+            Some(ByteRange {
+                start: 900,
+                end: 999
+            })
+        );
+        assert_eq!(
+            range("bytes=-100"),
+            Some(ByteRange {
+                start: 900,
+                end: 999
+            })
+        );
+        assert_eq!(range("bytes=990-2000").unwrap().content_length(), 10);
+        assert_eq!(
+            range("bytes=0-499").unwrap().content_range(1000),
+            "bytes 0-499/1000"
+        );
+
+        assert_eq!(range("bytes=0-1,5-9"), None);
+        assert_eq!(range("items=0-1"), None);
+        assert_eq!(range("bytes=9-1"), None);
+
+        let err = parse_range("bytes=1000-", 1000).unwrap_err();
+        assert_eq!(err.content_range(), "bytes */1000");
+        assert!(parse_range("bytes=-0", 1000).is_err());
+    }
+
+    #[test]
+    fn if_range_requires_a_strong_match() {
+        assert!(if_range("\"5f1e\"", "\"5f1e\""));
+        assert!(!if_range("W/\"5f1e\"", "\"5f1e\""));
+        assert!(!if_range("Wed, 01 May 2024 12:00:00 GMT", "\"5f1e\""));
+    }
+}
@@ -0,0 +1,118 @@
+//! Field-level validation of request payloads.
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::error::{ApiError, ErrorCode};
+
+/// Implemented by request payloads that need checks beyond deserialization.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Accumulates every failing field so clients can show all problems at once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Records `message` against `field` unless `ok` holds.
+    pub fn check(&mut self, ok: bool, field: &str, message: &str) {
+        if !ok {
+            self.add(field, message);
+        }
+    }
+
+    pub fn require_non_empty(&mut self, field: &str, value: &str) {
+        self.check(!value.trim().is_empty(), field, "must not be empty");
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// `Ok` when nothing was recorded, otherwise the collected errors.
+    pub fn finish(self) -> Result<(), Self> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        ApiError::new(ErrorCode::ValidationFailed, "request validation failed")
+            .with_details(json!({ "fields": errors.errors }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Rename {
+        name: String,
+        limit: u32,
+    }
+
+    impl Validate for Rename {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            errors.require_non_empty("name", &self.name);
+            errors.check(self.limit > 0, "limit", "must be positive");
+            errors.finish()
+        }
+    }
+
+    #[test]
+    fn collects_every_failing_field_into_a_422() {
+        let errors = Rename {
+            name: " ".into(),
+            limit: 0,
+        }
+        .validate()
+        .unwrap_err();
+        let fields = errors
+            .errors()
+            .iter()
+            .map(|e| e.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["name", "limit"]);
+
+        let api: ApiError = errors.into();
+        assert_eq!(api.status(), 422);
+        assert_eq!(
+            api.details.unwrap()["fields"][1]["message"],
+            "must be positive"
+        );
+    }
+
+    #[test]
+    fn valid_payload_passes() {
+        let payload = Rename {
+            name: "sales".into(),
+            limit: 10,
+        };
+        assert!(payload.validate().is_ok());
+    }
+}